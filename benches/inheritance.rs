@@ -0,0 +1,52 @@
+//! Rendering of layout-heavy templates using parents and blocks.
+//!
+//! Run with `cargo bench --bench inheritance`.
+use mustache::{Template, TemplateMap};
+
+use std::{collections::HashMap, hint::black_box, time::Instant};
+use serde_json::json;
+
+
+fn main() {
+    let base = format!(
+        "<html><head>{{{{$head}}}}<title>{{{{$title}}}}{{{{/title}}}}</title>{{{{/head}}}}</head>\n<body>{}{{{{$body}}}}{{{{/body}}}}</body></html>\n",
+        "<div class=\"static\">some layout text</div>\n".repeat(200)
+    );
+    let cell = format!(
+        "<td>{}{{{{$value}}}}{{{{/value}}}}</td>",
+        "{{#style}}<span class=\"{{.}}\"></span>{{/style}}\n".repeat(20)
+    );
+    let partials = TemplateMap::new(vec![
+        ("base", base.as_str()),
+        ("layout", "{{<base}}{{$title}}{{title}}{{/title}}{{/base}}"),
+        ("row", "{{<cell}}{{$value}}{{name}}{{/value}}{{/cell}}"),
+        ("cell", cell.as_str()),
+    ].into_iter().collect::<HashMap<_, _>>()).unwrap();
+    let page = Template::from(
+        "{{<layout}}{{$body}}<table>{{#rows}}<tr>{{>row}}</tr>\n{{/rows}}</table>{{/body}}{{/layout}}"
+    ).unwrap();
+    let data = json!({
+        "title": "report",
+        "rows": (0..100).map(|n| json!({ "name": format!("row {}", n) })).collect::<Vec<_>>()
+    });
+
+    bench("page with nested parents", 2_000, || page.render_with_partials(&data, &partials));
+
+    let nested = Template::from(
+        "{{#rows}}{{<cell}}{{$value}}{{name}}{{/value}}{{/cell}}{{/rows}}"
+    ).unwrap();
+    bench("parent in loop", 2_000, || nested.render_with_partials(&data, &partials));
+}
+
+fn bench(name: &str, iterations: u32, render: impl Fn() -> String) {
+    // warm up
+    for _ in 0..iterations / 10 {
+        black_box(render());
+    }
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(render());
+    }
+    let elapsed = start.elapsed();
+    println!("{:<30} {:>10.2?} per render", name, elapsed / iterations);
+}
@@ -0,0 +1,396 @@
+//! Derive macro for the `Context` trait of the `mustache` crate.
+//!
+//! The macro is re-exported by `mustache` when its `derive` feature is
+//! enabled, and is documented there.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    ext::IdentExt, parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Fields, Ident, LitStr,
+    Member, Path, Result, Type,
+};
+
+
+/// Derive `Context` for a struct or an enum, borrowing its fields.
+///
+/// Fields are exposed by name, tuple fields as `0`, `1`, ... while a
+/// newtype renders as its content. An enum variant is rendered as its
+/// name, with a section of that name selecting it.
+///
+/// Field attributes:
+/// - `#[mustache(rename = "name")]` exposes the field under another name,
+/// - `#[mustache(skip)]` hides the field,
+/// - `#[mustache(falsy)]` makes the value falsy when the field is.
+///
+/// Container attributes:
+/// - `#[mustache(method = "name")]` exposes a `fn name(&self) -> &T` as a
+///   field, which can be navigated as any other field,
+/// - `#[mustache(computed = "name")]` exposes a `fn name(&self) -> T` returning
+///   an owned value as a computed child (see `Context::computed`), which is
+///   rendered or used as a condition but not navigated,
+/// - `#[mustache(falsy = "path")]` uses a `fn(&Self) -> bool` for falsiness.
+///
+/// Methods are named on the type rather than marked in `impl` blocks, as a
+/// derive macro only sees the type definition.
+///
+/// # Sample
+///
+/// ```
+/// use mustache::{Template, Context};
+///
+/// #[derive(Context)]
+/// #[mustache(method = "first", computed = "total")]
+/// struct Order {
+///     #[mustache(rename = "ref")]
+///     id: u32,
+///     #[mustache(falsy)]
+///     items: Vec<Item>,
+/// }
+///
+/// impl Order {
+///     fn first(&self) -> &Item {
+///         &self.items[0]
+///     }
+///
+///     fn total(&self) -> f64 {
+///         self.items.iter().map(|item| item.price).sum()
+///     }
+/// }
+///
+/// #[derive(Context)]
+/// struct Item {
+///     name: &'static str,
+///     price: f64,
+/// }
+///
+/// let order = Order { id: 7, items: vec![Item { name: "a", price: 1.0 }, Item { name: "b", price: 0.5 }] };
+/// let template = Template::from("{{ref}}:{{#items}} {{name}}{{/items}} = {{total}} from {{first.name}}").unwrap();
+/// assert_eq!(template.render(&order), "7: a b = 1.5 from a");
+/// ```
+#[proc_macro_derive(Context, attributes(mustache))]
+pub fn derive_context(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+
+// attributes on the struct or enum
+#[derive(Default)]
+struct ContainerAttrs {
+    falsy: Option<Path>,
+    methods: Vec<Ident>,
+    computed: Vec<Ident>,
+}
+
+// attributes on a field or variant
+#[derive(Default)]
+struct ItemAttrs {
+    rename: Option<String>,
+    skip: bool,
+    falsy: bool,
+}
+
+fn container_attrs(attrs: &[Attribute]) -> Result<ContainerAttrs> {
+    let mut result = ContainerAttrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("mustache")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("falsy") {
+                let value: LitStr = meta.value()?.parse()?;
+                result.falsy = Some(value.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("method") {
+                let value: LitStr = meta.value()?.parse()?;
+                result.methods.push(value.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("computed") {
+                let value: LitStr = meta.value()?.parse()?;
+                result.computed.push(value.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `falsy = \"...\"`, `method = \"...\"` or `computed = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(result)
+}
+
+fn item_attrs(attrs: &[Attribute]) -> Result<ItemAttrs> {
+    let mut result = ItemAttrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("mustache")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let value: LitStr = meta.value()?.parse()?;
+                result.rename = Some(value.value());
+                Ok(())
+            } else if meta.path.is_ident("skip") {
+                result.skip = true;
+                Ok(())
+            } else if meta.path.is_ident("falsy") {
+                result.falsy = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename = \"...\"`, `skip` or `falsy`"))
+            }
+        })?;
+    }
+    Ok(result)
+}
+
+
+// a field exposed to templates
+struct Field {
+    name: String,
+    member: Member,
+    binding: Ident,
+    ty: Type,
+    falsy: bool,
+}
+
+fn fields(fields: &Fields) -> Result<Vec<Field>> {
+    let mut result = Vec::new();
+    for (idx, field) in fields.iter().enumerate() {
+        let attrs = item_attrs(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let (member, default_name, binding) = match &field.ident {
+            Some(ident) => {
+                let name = ident.unraw().to_string();
+                (Member::from(ident.clone()), name.clone(), format_ident!("__{}", name))
+            },
+            None => (Member::from(idx), idx.to_string(), format_ident!("__{}", idx)),
+        };
+        result.push(Field {
+            name: attrs.rename.unwrap_or(default_name),
+            member,
+            binding,
+            ty: field.ty.clone(),
+            falsy: attrs.falsy,
+        });
+    }
+    Ok(result)
+}
+
+// true for a single unnamed field, which is rendered as the type itself
+fn is_newtype(all: &Fields, exposed: &[Field]) -> bool {
+    matches!(all, Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1) && exposed.len() == 1
+}
+
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let attrs = container_attrs(&input.attrs)?;
+    let Expansion { child, children, value, falsy, computed, lambda, lazy, types } = match &input.data {
+        Data::Struct(data) => expand_struct(&data.fields)?,
+        Data::Enum(data) => {
+            let mut variants = Vec::new();
+            for variant in &data.variants {
+                let variant_attrs = item_attrs(&variant.attrs)?;
+                let name = variant_attrs.rename.unwrap_or_else(|| variant.ident.unraw().to_string());
+                let fields = fields(&variant.fields)?;
+                variants.push((variant, name, fields));
+            }
+            expand_enum(&variants)
+        },
+        Data::Union(_) => {
+            return Err(Error::new_spanned(&input.ident, "Context cannot be derived for unions"));
+        }
+    };
+
+    let method_keys = attrs.methods.iter().map(|method| method.unraw().to_string());
+    let methods = &attrs.methods;
+    let computed_keys = attrs.computed.iter().map(|method| method.unraw().to_string());
+    let computed_methods = &attrs.computed;
+    let falsy = match &attrs.falsy {
+        Some(path) => quote! { #path(self) },
+        None => falsy,
+    };
+
+    let mut generics = input.generics.clone();
+    if !generics.params.is_empty() {
+        let where_clause = generics.make_where_clause();
+        for ty in types {
+            where_clause.predicates.push(parse_quote! { #ty: ::mustache::Context });
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let ident = &input.ident;
+
+    Ok(quote! {
+        impl #impl_generics ::mustache::Context for #ident #ty_generics #where_clause {
+            fn child(
+                &self, name: &str, section: ::std::option::Option<(usize, usize)>
+            ) -> ::std::option::Option<::mustache::ContextRef<'_>> {
+                let _ = section;
+                match name {
+                    #( #method_keys => ::std::option::Option::Some(self.#methods() as ::mustache::ContextRef), )*
+                    _ => #child
+                }
+            }
+
+            fn children(&self) -> ::std::option::Option<::mustache::ContextRefIterator<'_>> {
+                #children
+            }
+
+            fn value(&self) -> ::mustache::ContextValue {
+                #value
+            }
+
+            fn is_falsy(&self) -> bool {
+                #falsy
+            }
+
+            fn computed(
+                &self, name: &str
+            ) -> ::std::option::Option<::std::boxed::Box<dyn ::mustache::Context + '_>> {
+                match name {
+                    #( #computed_keys => ::std::option::Option::Some(::std::boxed::Box::new(self.#computed_methods())), )*
+                    _ => #computed
+                }
+            }
+
+            fn lambda(&self) -> ::std::option::Option<&::mustache::Lambda> {
+                #lambda
+            }
+
+            fn lazy(&self) -> ::std::option::Option<::std::boxed::Box<dyn ::mustache::Context>> {
+                #lazy
+            }
+        }
+    })
+}
+
+// bodies of the Context methods, and types of the exposed fields
+struct Expansion {
+    child: TokenStream2,
+    children: TokenStream2,
+    value: TokenStream2,
+    falsy: TokenStream2,
+    computed: TokenStream2,
+    lambda: TokenStream2,
+    lazy: TokenStream2,
+    types: Vec<Type>,
+}
+
+fn expand_struct(all: &Fields) -> Result<Expansion> {
+    let exposed = fields(all)?;
+    let types = exposed.iter().map(|field| field.ty.clone()).collect();
+    if is_newtype(all, &exposed) {
+        let member = &exposed[0].member;
+        // every method is forwarded, the type standing for its field
+        return Ok(Expansion {
+            child: quote! { ::mustache::Context::child(&self.#member, name, section) },
+            children: quote! { ::mustache::Context::children(&self.#member) },
+            value: quote! { ::mustache::Context::value(&self.#member) },
+            falsy: quote! { ::mustache::Context::is_falsy(&self.#member) },
+            computed: quote! { ::mustache::Context::computed(&self.#member, name) },
+            lambda: quote! { ::mustache::Context::lambda(&self.#member) },
+            lazy: quote! { ::mustache::Context::lazy(&self.#member) },
+            types,
+        });
+    }
+    let names = exposed.iter().map(|field| &field.name);
+    let members = exposed.iter().map(|field| &field.member);
+    let falsy_members = exposed.iter().filter(|field| field.falsy).map(|field| &field.member);
+    Ok(Expansion {
+        child: quote! {
+            match name {
+                #( #names => ::std::option::Option::Some(&self.#members as ::mustache::ContextRef), )*
+                _ => ::std::option::Option::None
+            }
+        },
+        children: quote! { ::std::option::Option::None },
+        value: quote! { ::mustache::ContextValue::Text(::std::string::String::new()) },
+        falsy: quote! { false #( || ::mustache::Context::is_falsy(&self.#falsy_members) )* },
+        computed: quote! { ::std::option::Option::None },
+        lambda: quote! { ::std::option::Option::None },
+        lazy: quote! { ::std::option::Option::None },
+        types,
+    })
+}
+
+fn expand_enum(variants: &[(&syn::Variant, String, Vec<Field>)]) -> Expansion {
+    let mut child = Vec::new();
+    let mut children = Vec::new();
+    let mut value = Vec::new();
+    let mut falsy = Vec::new();
+    let mut computed = Vec::new();
+    let mut lambda = Vec::new();
+    let mut lazy = Vec::new();
+    let mut types = Vec::new();
+    for (variant, name, exposed) in variants {
+        let ident = &variant.ident;
+        types.extend(exposed.iter().map(|field| field.ty.clone()));
+        let pattern = match &variant.fields {
+            Fields::Named(_) => {
+                let members = exposed.iter().map(|field| &field.member);
+                let bindings = exposed.iter().map(|field| &field.binding);
+                quote! { Self::#ident { #( #members: #bindings, )* .. } }
+            },
+            Fields::Unnamed(_) => {
+                let mut bindings = Vec::new();
+                for idx in 0..variant.fields.len() {
+                    match exposed.iter().find(|field| field.member == Member::from(idx)) {
+                        Some(field) => {
+                            let binding = &field.binding;
+                            bindings.push(quote! { #binding })
+                        },
+                        None => bindings.push(quote! { _ }),
+                    }
+                }
+                quote! { Self::#ident( #( #bindings ),* ) }
+            },
+            Fields::Unit => quote! { Self::#ident },
+        };
+        if is_newtype(&variant.fields, exposed) {
+            let binding = &exposed[0].binding;
+            child.push(quote! {
+                #pattern => if name == #name {
+                    ::std::option::Option::Some(#binding as ::mustache::ContextRef)
+                } else {
+                    ::mustache::Context::child(#binding, name, section)
+                }
+            });
+            children.push(quote! { #pattern => ::mustache::Context::children(#binding) });
+            value.push(quote! { #pattern => ::mustache::Context::value(#binding) });
+            falsy.push(quote! { #pattern => ::mustache::Context::is_falsy(#binding) });
+            computed.push(quote! { #pattern => ::mustache::Context::computed(#binding, name) });
+            lambda.push(quote! { #pattern => ::mustache::Context::lambda(#binding) });
+            lazy.push(quote! { #pattern => ::mustache::Context::lazy(#binding) });
+        } else {
+            let names = exposed.iter().map(|field| &field.name);
+            let bindings = exposed.iter().map(|field| &field.binding);
+            let falsy_bindings = exposed.iter().filter(|field| field.falsy).map(|field| &field.binding);
+            child.push(quote! {
+                #pattern => match name {
+                    #name => ::std::option::Option::Some(self as ::mustache::ContextRef),
+                    #( #names => ::std::option::Option::Some(#bindings as ::mustache::ContextRef), )*
+                    _ => ::std::option::Option::None
+                }
+            });
+            children.push(quote! { #pattern => ::std::option::Option::None });
+            value.push(quote! {
+                #pattern => ::mustache::ContextValue::Text(::std::string::String::from(#name))
+            });
+            falsy.push(quote! {
+                #pattern => false #( || ::mustache::Context::is_falsy(#falsy_bindings) )*
+            });
+            computed.push(quote! { #pattern => ::std::option::Option::None });
+            lambda.push(quote! { #pattern => ::std::option::Option::None });
+            lazy.push(quote! { #pattern => ::std::option::Option::None });
+        }
+    }
+    // not every arm uses all of its bindings
+    let allow = quote! { #[allow(unused_variables)] };
+    Expansion {
+        child: quote! { { #allow match self { #( #child, )* } } },
+        children: quote! { { #allow match self { #( #children, )* } } },
+        value: quote! { { #allow match self { #( #value, )* } } },
+        falsy: quote! { { #allow match self { #( #falsy, )* } } },
+        computed: quote! { { #allow match self { #( #computed, )* } } },
+        lambda: quote! { { #allow match self { #( #lambda, )* } } },
+        lazy: quote! { { #allow match self { #( #lazy, )* } } },
+        types,
+    }
+}
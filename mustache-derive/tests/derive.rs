@@ -0,0 +1,221 @@
+use mustache::{Template, Context, Lambda, LazyContext};
+use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+
+#[derive(Context)]
+struct Person {
+    name: String,
+    #[mustache(rename = "years")]
+    age: u8,
+    #[mustache(skip)]
+    #[allow(dead_code)]
+    password: String,
+    address: Option<Address>,
+    tags: Vec<&'static str>,
+}
+
+#[derive(Context)]
+struct Address {
+    city: String,
+}
+
+fn person() -> Person {
+    Person {
+        name: "Ann".to_owned(),
+        age: 42,
+        password: "secret".to_owned(),
+        address: Some(Address { city: "Paris".to_owned() }),
+        tags: vec!["a", "b"],
+    }
+}
+
+#[test]
+fn struct_fields() {
+    let template = Template::from(
+        "{{name}} {{years}} {{age}}{{password}} {{address.city}}{{#tags}} {{.}}{{/tags}}"
+    ).unwrap();
+    assert_eq!(template.render(&person()), "Ann 42  Paris a b");
+}
+
+#[test]
+fn missing_option_is_falsy() {
+    let template = Template::from("{{#address}}{{city}}{{/address}}{{^address}}nowhere{{/address}}").unwrap();
+    let mut person = person();
+    assert_eq!(template.render(&person), "Paris");
+    person.address = None;
+    assert_eq!(template.render(&person), "nowhere");
+}
+
+
+#[derive(Context)]
+#[mustache(method = "given", computed = "full_name", computed = "initials", computed = "is_short")]
+struct Name {
+    first: String,
+    last: String,
+}
+
+impl Name {
+    fn new(first: &str, last: &str) -> Self {
+        Name { first: first.to_owned(), last: last.to_owned() }
+    }
+
+    fn given(&self) -> &String {
+        &self.first
+    }
+
+    fn full_name(&self) -> String {
+        format!("{} {}", self.first, self.last)
+    }
+
+    fn initials(&self) -> String {
+        format!("{}{}", &self.first[..1], &self.last[..1])
+    }
+
+    fn is_short(&self) -> bool {
+        self.first.len() + self.last.len() < 8
+    }
+}
+
+#[test]
+fn methods_as_fields() {
+    let template = Template::from("{{full_name}} ({{initials}}) {{given}}").unwrap();
+    assert_eq!(template.render(&Name::new("John", "Doe")), "John Doe (JD) John");
+}
+
+#[derive(Context)]
+struct Team {
+    names: Vec<Name>,
+    owner: Name,
+    initials: &'static str,
+}
+
+#[test]
+fn computed_values_in_sections() {
+    let template = Template::from(concat!(
+        "{{#names}}{{#is_short}}{{given}}{{/is_short}}{{^is_short}}{{full_name}}{{/is_short}};{{/names}}",
+        " {{owner.full_name}}"
+    )).unwrap();
+    let team = Team {
+        names: vec![Name::new("Ann", "Lee"), Name::new("Johanna", "Smith")],
+        owner: Name::new("Bob", "Ray"),
+        initials: "team",
+    };
+    assert_eq!(template.render(&team), "Ann;Johanna Smith; Bob Ray");
+
+    // fields anywhere in the stack come first
+    let template = Template::from("{{#owner}}{{initials}}{{/owner}}").unwrap();
+    assert_eq!(template.render(&team), "team");
+}
+
+
+#[derive(Context)]
+struct Cart {
+    owner: String,
+    #[mustache(falsy)]
+    count: usize,
+}
+
+#[derive(Context)]
+#[mustache(falsy = "Stock::is_empty")]
+struct Stock {
+    items: HashMap<String, u32>,
+}
+
+impl Stock {
+    fn is_empty(&self) -> bool {
+        self.items.values().all(|count| *count == 0)
+    }
+}
+
+#[test]
+fn custom_falsiness() {
+    let template = Template::from("{{#cart}}{{owner}}{{/cart}}{{^cart}}empty{{/cart}}").unwrap();
+    let cart = |count| HashMap::from([("cart".to_owned(), Cart { owner: "x".to_owned(), count })]);
+    assert_eq!(template.render(&cart(1)), "x");
+    assert_eq!(template.render(&cart(0)), "empty");
+
+    let template = Template::from("{{#stock}}{{items.a}}{{/stock}}{{^stock}}empty{{/stock}}").unwrap();
+    let stock = |count| HashMap::from([
+        ("stock".to_owned(), Stock { items: HashMap::from([("a".to_owned(), count)]) })
+    ]);
+    assert_eq!(template.render(&stock(3)), "3");
+    assert_eq!(template.render(&stock(0)), "empty");
+}
+
+
+#[derive(Context)]
+struct Id(u32);
+
+#[derive(Context)]
+struct Pair(String, #[mustache(skip)] #[allow(dead_code)] bool, Id);
+
+#[derive(Context)]
+struct Wrapper<T>(T);
+
+#[test]
+fn tuple_structs() {
+    let template = Template::from("{{.}}").unwrap();
+    assert_eq!(template.render(&Id(3)), "3");
+    assert_eq!(template.render(&Wrapper(Id(4))), "4");
+
+    let template = Template::from("{{0}}-{{1}}-{{2}}").unwrap();
+    assert_eq!(template.render(&Pair("a".to_owned(), true, Id(5))), "a--5");
+
+    let template = Template::from("{{#id}}yes{{/id}}{{^id}}no{{/id}}").unwrap();
+    assert_eq!(template.render(&HashMap::from([("id".to_owned(), Id(0))])), "no");
+    assert_eq!(template.render(&HashMap::from([("id".to_owned(), Wrapper(Id(1)))])), "yes");
+}
+
+#[test]
+fn newtypes_forward_lambdas_and_lazy_contexts() {
+    let template = Template::from("{{#upper}}a{{x}}{{/upper}}").unwrap();
+    let data = HashMap::from([
+        ("upper".to_owned(), Wrapper(Lambda::new1(|text| text.to_uppercase()))),
+    ]);
+    assert_eq!(template.render(&data), "A");
+
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+    let rows = Wrapper(LazyContext::new(move || {
+        counter.set(counter.get() + 1);
+        vec![counter.get()]
+    }));
+    let template = Template::from("{{#rows}}{{.}}{{/rows}} {{#rows}}{{.}}{{/rows}}").unwrap();
+    assert_eq!(template.render(&HashMap::from([("rows".to_owned(), rows)])), "1 1");
+    assert_eq!(calls.get(), 1);
+}
+
+
+#[derive(Context)]
+enum Shape {
+    Point,
+    Circle(f64),
+    #[mustache(rename = "rect")]
+    Rectangle { width: u32, height: u32 },
+    Segment(u32, u32),
+}
+
+#[test]
+fn enums() {
+    let template = Template::from(concat!(
+        "{{#Point}}point{{/Point}}",
+        "{{#Circle}}circle {{.}}{{/Circle}}",
+        "{{#rect}}{{width}}x{{height}}{{/rect}}",
+        "{{#Segment}}{{0}}..{{1}}{{/Segment}}",
+        " [{{.}}]"
+    )).unwrap();
+    assert_eq!(template.render(&Shape::Point), "point [Point]");
+    assert_eq!(template.render(&Shape::Circle(1.5)), "circle 1.5 [1.5]");
+    assert_eq!(template.render(&Shape::Rectangle { width: 2, height: 3 }), "2x3 [rect]");
+    assert_eq!(template.render(&Shape::Segment(4, 6)), "4..6 [Segment]");
+}
+
+#[test]
+fn enums_in_lists() {
+    let template = Template::from("{{#shapes}}{{^Point}}{{.}};{{/Point}}{{/shapes}}").unwrap();
+    let data = HashMap::from([(
+        "shapes".to_owned(),
+        vec![Shape::Point, Shape::Circle(2.0), Shape::Segment(0, 1)]
+    )]);
+    assert_eq!(template.render(&data), "2;Segment;");
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+
+use crate::template::{Segment, Segments};
+use crate::Filter;
+
+
+/// Location of a node in the template source.
+///
+/// `start` and `end` are byte offsets, tags including their delimiters.
+/// `line` and `column` locate `start`, both starting at 1 and the column
+/// counting characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+
+/// Node of a compiled [Template](crate::Template).
+///
+/// Comments and set delimiters tags are not part of the tree, and text
+/// excludes the whitespace of standalone lines.
+#[derive(Clone, Copy, Debug)]
+pub enum Node<'t> {
+    /// Text rendered as is.
+    Text { text: &'t str, span: Span },
+    /// `{{name}}`, or `{{{name}}}` and `{{&name}}` when not escaped.
+    /// Filters are empty unless compiled with
+    /// [CompileOptions::filters](crate::CompileOptions::filters).
+    Variable { name: &'t str, filters: &'t [Filter], escaped: bool, span: Span },
+    /// `{{#name}}...{{/name}}`.
+    Section { name: &'t str, children: Nodes<'t>, span: Span },
+    /// `{{^name}}...{{/name}}`.
+    InvertedSection { name: &'t str, children: Nodes<'t>, span: Span },
+    /// `{{#?name}}...{{/?name}}`.
+    SeqCheckSection { name: &'t str, children: Nodes<'t>, span: Span },
+    /// `{{$name}}...{{/name}}`.
+    Block { name: &'t str, children: Nodes<'t>, span: Span },
+    /// `{{>name}}`, or `{{>*name}}` when dynamic.
+    Partial { name: &'t str, dynamic: bool, span: Span },
+    /// `{{<name}}...{{/name}}`, or `{{<*name}}` when dynamic.
+    /// The children are the blocks of the parent tag.
+    Parent { name: &'t str, dynamic: bool, children: Nodes<'t>, span: Span },
+}
+
+impl<'t> Node<'t> {
+    fn from(segment: &'t Segment) -> Self {
+        match segment {
+            Segment::Text(text, _, span) =>
+                Node::Text { text, span: *span },
+            Segment::Value(name, filters, escaped, _, span) =>
+                Node::Variable { name, filters, escaped: *escaped, span: *span },
+            Segment::Section(name, _, _, is_seqcheck, _, _, children, span) => {
+                let children = Nodes(children);
+                if *is_seqcheck {
+                    Node::SeqCheckSection { name, children, span: *span }
+                } else {
+                    Node::Section { name, children, span: *span }
+                }
+            },
+            Segment::InvertedSection(name, children, span) =>
+                Node::InvertedSection { name, children: Nodes(children), span: *span },
+            Segment::Block(name, children, span) =>
+                Node::Block { name, children: Nodes(children), span: *span },
+            Segment::Partial(name, _, dynamic, None, span) =>
+                Node::Partial { name, dynamic: *dynamic, span: *span },
+            Segment::Partial(name, _, dynamic, Some(blocks), span) =>
+                Node::Parent { name, dynamic: *dynamic, children: Nodes(blocks), span: *span },
+        }
+    }
+
+    /// Name in the tag, None for text.
+    pub fn name(&self) -> Option<&'t str> {
+        match self {
+            Node::Text { .. } => None,
+            Node::Variable { name, .. } | Node::Section { name, .. } |
+            Node::InvertedSection { name, .. } | Node::SeqCheckSection { name, .. } |
+            Node::Block { name, .. } | Node::Partial { name, .. } |
+            Node::Parent { name, .. } => Some(name),
+        }
+    }
+
+    /// Nested nodes of sections, blocks and parents.
+    pub fn children(&self) -> Option<Nodes<'t>> {
+        match self {
+            Node::Section { children, .. } | Node::InvertedSection { children, .. } |
+            Node::SeqCheckSection { children, .. } | Node::Block { children, .. } |
+            Node::Parent { children, .. } => Some(*children),
+            _ => None
+        }
+    }
+
+    /// Location of the node, the opening tag for nodes with children.
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Text { span, .. } | Node::Variable { span, .. } | Node::Section { span, .. } |
+            Node::InvertedSection { span, .. } | Node::SeqCheckSection { span, .. } |
+            Node::Block { span, .. } | Node::Partial { span, .. } |
+            Node::Parent { span, .. } => *span,
+        }
+    }
+}
+
+
+/// Sequence of [Node] in a template or in a node with children.
+#[derive(Clone, Copy)]
+pub struct Nodes<'t>(&'t [Segment]);
+
+impl<'t> Nodes<'t> {
+    pub(crate) fn new(segments: &'t Segments) -> Self {
+        Nodes(segments)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Node<'t>> {
+        self.0.get(index).map(Node::from)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Node<'t>> {
+        self.0.iter().map(Node::from)
+    }
+
+    /// Visit nodes and their children depth first, in source order.
+    pub fn walk(&self, visitor: &mut dyn Visitor<'t>) {
+        for node in self.iter() {
+            if visitor.enter(&node) {
+                if let Some(children) = node.children() {
+                    children.walk(visitor);
+                }
+            }
+            visitor.leave(&node);
+        }
+    }
+}
+
+impl fmt::Debug for Nodes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+
+/// Walk a template tree, see [Template::visit](crate::Template::visit).
+///
+/// # Sample
+///
+/// ```
+/// use mustache::{Template, Visitor, Node};
+///
+/// // names used in interpolation position, with the enclosing sections
+/// struct Names(Vec<String>, Vec<String>);
+///
+/// impl<'t> Visitor<'t> for Names {
+///     fn enter(&mut self, node: &Node<'t>) -> bool {
+///         match node {
+///             Node::Variable { name, .. } => self.1.push(self.0.join(".") + "/" + name),
+///             Node::Section { name, .. } => self.0.push(name.to_string()),
+///             _ => {}
+///         }
+///         true
+///     }
+///
+///     fn leave(&mut self, node: &Node<'t>) {
+///         if let Node::Section { .. } = node {
+///             self.0.pop();
+///         }
+///     }
+/// }
+///
+/// let template = Template::from("{{a}}{{#b}}{{#c}}{{d}}{{/c}}{{/b}}").unwrap();
+/// let mut names = Names(vec![], vec![]);
+/// template.visit(&mut names);
+/// assert_eq!(names.1, ["/a", "b.c/d"]);
+/// ```
+pub trait Visitor<'t> {
+    /// Called for each node before its children, which are skipped if
+    /// the result is false.
+    fn enter(&mut self, node: &Node<'t>) -> bool {
+        let _ = node;
+        true
+    }
+
+    /// Called for each node after its children.
+    fn leave(&mut self, node: &Node<'t>) {
+        let _ = node;
+    }
+}
@@ -0,0 +1,204 @@
+use mustache::{Template, FileSystemStore, ErrorKind, LintKind, ReferenceKind};
+use serde_json::json;
+
+use std::{collections::HashSet, fs, io, path::{Path, PathBuf}};
+
+use crate::{Failure, usage};
+
+
+#[derive(Default)]
+pub struct CheckArgs {
+    directories: Vec<PathBuf>,
+    extension: Option<String>,
+    json: bool,
+}
+
+impl CheckArgs {
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Option<Self>, Failure> {
+        let mut result = CheckArgs::default();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            let mut value = |option: &str| match args.next() {
+                Some(value) => Ok(value),
+                None => usage(format!("missing value for {}", option))
+            };
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "-e" | "--extension" => result.extension = Some(value(&arg)?),
+                "-f" | "--format" => match value(&arg)?.as_str() {
+                    "human" => result.json = false,
+                    "json" => result.json = true,
+                    format => return usage(format!("unknown format {}", format)),
+                },
+                option if option.starts_with('-') => return usage(format!("unknown option {}", option)),
+                _ => result.directories.push(arg.into()),
+            }
+        }
+        if result.directories.is_empty() {
+            result.directories.push(".".into());
+        }
+        Ok(Some(result))
+    }
+}
+
+
+// error or warning in a template file
+struct Diagnostic {
+    path: String,
+    line: usize,
+    column: usize,
+    is_error: bool,
+    code: &'static str,
+    message: String,
+    tag: String,
+}
+
+impl Diagnostic {
+    fn severity(&self) -> &'static str {
+        if self.is_error { "error" } else { "warning" }
+    }
+}
+
+
+// compile every template in the directories, and look for suspicious
+// constructs and missing partials when linting
+pub fn check(args: CheckArgs, lint: bool) -> Result<(), Failure> {
+    let extension = args.extension.as_deref().unwrap_or("mustache").trim_start_matches('.');
+    // every file is checked, including those shadowed by a file of the same
+    // name in an earlier directory, the store only resolves partial names
+    let mut paths = Vec::new();
+    for directory in &args.directories {
+        scan(directory, extension, &mut paths).map_err(|err| format!("{}: {}", directory.display(), err))?;
+    }
+    paths.sort();
+    paths.dedup();
+    let store = FileSystemStore::new(&args.directories, extension)?;
+    let names = store.names().collect::<HashSet<_>>();
+
+    let mut diagnostics = Vec::new();
+    for path in &paths {
+        let source = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let mut found = Template::check(&source).into_iter()
+            .map(|err| Diagnostic {
+                line: err.line,
+                column: err.column,
+                is_error: true,
+                code: error_code(&err.kind),
+                message: err.kind.to_string(),
+                tag: err.tag,
+                path: String::new(),
+            })
+            .collect::<Vec<_>>();
+        if lint {
+            lint_file(&source, &names, &mut found);
+        }
+        found.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+        for mut diagnostic in found {
+            diagnostic.path = path.display().to_string();
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    let errors = diagnostics.iter().filter(|diagnostic| diagnostic.is_error).count();
+    let warnings = diagnostics.len() - errors;
+    if args.json {
+        let diagnostics = diagnostics.iter().map(|diagnostic| json!({
+            "path": diagnostic.path,
+            "line": diagnostic.line,
+            "column": diagnostic.column,
+            "severity": diagnostic.severity(),
+            "code": diagnostic.code,
+            "message": diagnostic.message,
+            "tag": diagnostic.tag,
+        })).collect::<Vec<_>>();
+        let report = json!({
+            "files": paths.len(),
+            "errors": errors,
+            "warnings": warnings,
+            "diagnostics": diagnostics,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for diagnostic in &diagnostics {
+            println!(
+                "{}:{}:{}: {}: {} `{}`",
+                diagnostic.path, diagnostic.line, diagnostic.column,
+                diagnostic.severity(), diagnostic.message, diagnostic.tag
+            );
+        }
+        eprintln!("{} files, {} errors, {} warnings", paths.len(), errors, warnings);
+    }
+    match (errors, warnings) {
+        (0, 0) => Ok(()),
+        (0, _) if !lint => Ok(()),
+        _ => Err(Failure::Silent)
+    }
+}
+
+// collect files with extension under dir as FileSystemStore does, without
+// following symbolic links to directories
+fn scan(dir: &Path, extension: &str, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let suffix = format!(".{}", extension);
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            scan(&path, extension, files)?;
+        } else if path.is_file() && entry.file_name().to_str()
+            .is_some_and(|name| name.len() > suffix.len() && name.ends_with(&suffix)) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn lint_file(source: &str, names: &HashSet<&str>, found: &mut Vec<Diagnostic>) {
+    for lint in Template::lint(source) {
+        found.push(Diagnostic {
+            line: lint.span.line,
+            column: lint.span.column,
+            is_error: false,
+            code: lint_code(lint.kind),
+            message: lint.kind.to_string(),
+            tag: lint.tag,
+            path: String::new(),
+        });
+    }
+    // dynamic partials are resolved at render time
+    let Ok(template) = Template::from(source) else {
+        return;
+    };
+    for reference in template.references() {
+        if reference.kind == ReferenceKind::Partial && !names.contains(reference.name.as_str()) {
+            let span = reference.span;
+            found.push(Diagnostic {
+                line: span.line,
+                column: span.column,
+                is_error: false,
+                code: "missing-partial",
+                message: "missing partial".to_owned(),
+                tag: source[span.start..span.end].to_owned(),
+                path: String::new(),
+            });
+        }
+    }
+}
+
+fn error_code(kind: &ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::MissingCloseDelimiter => "missing-close-delimiter",
+        ErrorKind::InvalidDelimiters => "invalid-delimiters",
+        ErrorKind::UnexpectedEndSection => "unexpected-end-section",
+        ErrorKind::UnclosedSection => "unclosed-section",
+        ErrorKind::InvalidFilter => "invalid-filter",
+    }
+}
+
+fn lint_code(kind: LintKind) -> &'static str {
+    match kind {
+        LintKind::UnusedDelimiters => "unused-delimiters",
+        LintKind::WrongCloseQualifier => "wrong-close-qualifier",
+        LintKind::BlockOutsideParent => "block-outside-parent",
+        LintKind::DuplicateBlock => "duplicate-block",
+    }
+}
@@ -0,0 +1,251 @@
+use mustache::{
+    Template, TemplateStore, FileSystemStore, RenderOptions, RenderError, Context, JsonValue, YamlValue
+};
+
+mod check;
+
+use check::CheckArgs;
+
+use std::{
+    env, fmt, fs, io::{self, Read, Write}, path::{Path, PathBuf}, process::ExitCode, sync::{Arc, OnceLock}
+};
+
+
+const USAGE: &str = "\
+Usage: mustache render [TEMPLATE] [OPTIONS]
+       mustache check [DIR]... [OPTIONS]
+       mustache lint [DIR]... [OPTIONS]
+
+Render TEMPLATE, or the standard input if not given.
+
+Render options:
+  -d, --data FILE         JSON (.json) or YAML data, `-` for the standard input
+  -p, --partials DIR      directory of partials, may be repeated
+  -e, --extension EXT     extension of partial files [default: mustache]
+  -o, --output FILE       write to FILE rather than the standard output
+      --strict            fail on missing names and partials
+  -h, --help              print this help
+
+Check compiles the templates in each DIR (the current directory by default),
+reporting errors. Lint also reports suspicious constructs and missing partials
+as warnings, failing if there are any.
+
+Check and lint options:
+  -e, --extension EXT     extension of template files [default: mustache]
+  -f, --format FORMAT     `human` (the default) or `json`
+";
+
+
+// failure of a command, the exit code being 2 for usage errors
+enum Failure {
+    Usage(String),
+    Error(String),
+    // already reported
+    Silent,
+}
+
+impl<E: fmt::Display> From<E> for Failure {
+    fn from(err: E) -> Self {
+        Failure::Error(err.to_string())
+    }
+}
+
+fn usage<T>(message: impl Into<String>) -> Result<T, Failure> {
+    Err(Failure::Usage(message.into()))
+}
+
+
+#[derive(Default)]
+struct RenderArgs {
+    template: Option<PathBuf>,
+    data: Option<PathBuf>,
+    partials: Vec<PathBuf>,
+    extension: Option<String>,
+    output: Option<PathBuf>,
+    strict: bool,
+}
+
+impl RenderArgs {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Option<Self>, Failure> {
+        let mut result = RenderArgs::default();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            let mut value = |option: &str| match args.next() {
+                Some(value) => Ok(value),
+                None => usage(format!("missing value for {}", option))
+            };
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "-d" | "--data" => result.data = Some(value(&arg)?.into()),
+                "-p" | "--partials" => result.partials.push(value(&arg)?.into()),
+                "-e" | "--extension" => result.extension = Some(value(&arg)?),
+                "-o" | "--output" => result.output = Some(value(&arg)?.into()),
+                "--strict" => result.strict = true,
+                option if option.starts_with('-') && option != "-" => {
+                    return usage(format!("unknown option {}", option));
+                },
+                _ if result.template.is_some() => return usage(format!("unexpected argument {}", arg)),
+                _ => result.template = Some(arg.into()),
+            }
+        }
+        Ok(Some(result))
+    }
+}
+
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let result = match args.next().as_deref() {
+        Some("render") => render(args),
+        Some(command @ ("check" | "lint")) => match CheckArgs::parse(args) {
+            Ok(Some(args)) => check::check(args, command == "lint"),
+            Ok(None) => {
+                print!("{}", USAGE);
+                Ok(())
+            },
+            Err(failure) => Err(failure)
+        },
+        Some("-h" | "--help") => {
+            print!("{}", USAGE);
+            Ok(())
+        },
+        Some(command) => usage(format!("unknown command {}", command)),
+        None => usage("missing command"),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure::Usage(message)) => {
+            eprintln!("mustache: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        },
+        Err(Failure::Error(message)) => {
+            eprintln!("mustache: {}", message);
+            ExitCode::FAILURE
+        },
+        Err(Failure::Silent) => ExitCode::FAILURE
+    }
+}
+
+fn render(args: impl Iterator<Item = String>) -> Result<(), Failure> {
+    let Some(args) = RenderArgs::parse(args)? else {
+        print!("{}", USAGE);
+        return Ok(());
+    };
+    let stdin_template = is_stdin(args.template.as_deref());
+    if stdin_template && args.data.as_deref().is_some_and(|path| is_stdin(Some(path))) {
+        return usage("template and data cannot both be read from the standard input");
+    }
+
+    let (name, source) = read(args.template.as_deref())?;
+    let template = Template::from(&source).map_err(|mut err| {
+        err.name = Some(name);
+        err
+    })?;
+    let data = match &args.data {
+        Some(path) => Data::read(path)?,
+        None => Data::Json(JsonValue::Object(Default::default())),
+    };
+
+    let extension = args.extension.as_deref().unwrap_or("mustache");
+    let store = FileSystemStore::new(&args.partials, extension)
+        .map_err(|err| format!("partials: {}", err))?;
+    let partials = Partials { store, error: OnceLock::new() };
+
+    let options = RenderOptions { strict: args.strict, ..Default::default() };
+    let render = |out: &mut dyn Write| {
+        let mut out = io::BufWriter::new(out);
+        template.write_to_with_options(data.context(), Some(&partials), &options, &mut out)?;
+        out.flush()
+    };
+    // the output is written as it is produced
+    let result = match &args.output {
+        Some(path) => fs::File::create(path).and_then(|mut file| render(&mut file)),
+        None => render(&mut io::stdout().lock()),
+    };
+    // a broken partial is reported rather than the error it caused
+    if let Some(err) = partials.error.into_inner() {
+        return Err(Failure::Error(err));
+    }
+    result.map_err(|err| match err.downcast::<RenderError>() {
+        Ok(mut err) => {
+            err.template.get_or_insert(name_of(args.template.as_deref()));
+            Failure::Error(err.to_string())
+        },
+        Err(err) => match &args.output {
+            Some(path) => Failure::Error(format!("{}: {}", path.display(), err)),
+            None => Failure::from(err),
+        }
+    })
+}
+
+// partials loaded as the render resolves them, keeping the first failure
+// so that a broken file is reported rather than rendered as missing
+struct Partials {
+    store: FileSystemStore,
+    error: OnceLock<String>,
+}
+
+impl TemplateStore for Partials {
+    fn get(&self, name: &str) -> Option<Arc<Template>> {
+        match self.store.load(name) {
+            Ok(template) => template,
+            Err(err) => {
+                let _ = self.error.set(err.to_string());
+                None
+            }
+        }
+    }
+}
+
+
+enum Data {
+    Json(JsonValue),
+    Yaml(YamlValue),
+}
+
+impl Data {
+    // JSON for .json files, YAML otherwise (which also reads most JSON)
+    fn read(path: &Path) -> Result<Self, Failure> {
+        let (name, text) = read(Some(path))?;
+        let is_json = path.extension().is_some_and(|extension| extension == "json");
+        let data = if is_json {
+            serde_json::from_str(&text).map(Data::Json).map_err(|err| format!("{}: {}", name, err))?
+        } else {
+            serde_yaml::from_str(&text).map(Data::Yaml).map_err(|err| format!("{}: {}", name, err))?
+        };
+        Ok(data)
+    }
+
+    fn context(&self) -> &dyn Context {
+        match self {
+            Data::Json(value) => value,
+            Data::Yaml(value) => value,
+        }
+    }
+}
+
+
+fn is_stdin(path: Option<&Path>) -> bool {
+    path.is_none_or(|path| path == Path::new("-"))
+}
+
+fn name_of(path: Option<&Path>) -> String {
+    match path {
+        Some(path) if !is_stdin(Some(path)) => path.display().to_string(),
+        _ => "<stdin>".to_owned()
+    }
+}
+
+// name for messages and content of a file or of the standard input
+fn read(path: Option<&Path>) -> Result<(String, String), Failure> {
+    let name = name_of(path);
+    let mut text = String::new();
+    let result = match path {
+        Some(path) if !is_stdin(Some(path)) => fs::read_to_string(path).map(|read| text = read),
+        _ => io::stdin().read_to_string(&mut text).map(|_| ()),
+    };
+    match result {
+        Ok(()) => Ok((name, text)),
+        Err(err) => Err(Failure::Error(format!("{}: {}", name, err)))
+    }
+}
@@ -1,3 +1,9 @@
+use std::cell::OnceCell;
+use std::collections::HashMap;
+
+use crate::Lambda;
+
+
 /// Adapter to render an external type into a Mustache template.
 /// 
 /// The trait is used by the rendering engine to obtain context data and navigate
@@ -63,6 +69,37 @@ pub trait Context {
 
     /// Indicate if the context is falsy.
     fn is_falsy(&self) -> bool;
+
+    /// Get the [Lambda] the context stands for, None (the default) for data.
+    /// 
+    /// A lambda is called by the renderer with the unprocessed content of
+    /// the section where it is used, see [Lambdas](crate::Lambdas).
+    fn lambda(&self) -> Option<&Lambda> {
+        None
+    }
+
+    /// Get a child computed on request, None (the default) if there is none.
+    /// 
+    /// Unlike [Context::child], the result is owned, which allows exposing
+    /// values computed by methods. A computed child is used only for names
+    /// that do not resolve to a child of any context in the stack. It is
+    /// rendered in interpolation position, and used as a condition in
+    /// section and inverted section position, but cannot be navigated:
+    /// sequences are not iterated and dotted names do not resolve in it.
+    fn computed(&self, name: &str) -> Option<Box<dyn Context + '_>> {
+        let _ = name;
+        None
+    }
+
+    /// Produce the context a lazy context stands for, None (the default)
+    /// for other contexts.
+    /// 
+    /// The renderer calls this the first time the context is used in a
+    /// render, and uses the result in its place until the end of the render,
+    /// see [LazyContext](crate::LazyContext).
+    fn lazy(&self) -> Option<Box<dyn Context>> {
+        None
+    }
 }
 
 #[derive(PartialEq, Debug)]
@@ -76,22 +113,35 @@ pub type ContextRefIterator<'a> = Box<dyn Iterator<Item = ContextRef<'a>> + 'a>;
 
     
 struct Frame<'a> {
+    // context the frame was created from, a sequence for iterators
+    context: ContextRef<'a>,
     current: Option<ContextRef<'a>>,
     iterator: Option<ContextRefIterator<'a>>,
+    // position of the current item, and number of items once counted
+    index: usize,
+    length: Option<usize>,
 }
 
 impl<'a> Frame<'a> {
     fn new_from_single(context: ContextRef<'a>) -> Self {
         Frame {
+            context,
             current: Some(context),
-            iterator: None
+            iterator: None,
+            index: 0,
+            length: None,
         }
     }
 
-    fn new_from_iterator(mut iterator: ContextRefIterator<'a>) -> Self {
+    fn new_from_iterator(
+        context: ContextRef<'a>, mut iterator: ContextRefIterator<'a>, lazy: &mut Lazy<'a>
+    ) -> Self {
         Frame {
-            current: iterator.next(),
-            iterator: Some(iterator)
+            context,
+            current: iterator.next().map(|item| lazy.resolve(item)),
+            iterator: Some(iterator),
+            index: 0,
+            length: None,
         }
     }
 
@@ -99,27 +149,96 @@ impl<'a> Frame<'a> {
         self.current.as_ref()
     }
 
-    fn next(&mut self) -> bool {
+    fn next(&mut self, lazy: &mut Lazy<'a>) -> bool {
+        self.index += 1;
         if let Some(mut iterator) = self.iterator.take() {
-            self.current = iterator.next();
+            self.current = iterator.next().map(|item| lazy.resolve(item));
             self.iterator = Some(iterator);
         } else {
             self.current = None;
         }
         self.current.is_some()
     }
+
+    // items in the sequence, iterating it again the first time
+    fn length(&mut self) -> usize {
+        let context = self.context;
+        *self.length.get_or_insert_with(|| context.children().map_or(0, |children| children.count()))
+    }
+}
+
+
+// value of a loop metadata name, see [Stack::loop_value]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum LoopValue {
+    Number(usize),
+    Flag(bool),
+}
+
+impl LoopValue {
+    pub(crate) fn text(self) -> String {
+        match self {
+            LoopValue::Number(n) => n.to_string(),
+            LoopValue::Flag(b) => b.to_string(),
+        }
+    }
+
+    // zero is falsy, as for JSON numbers
+    pub(crate) fn is_falsy(self) -> bool {
+        matches!(self, LoopValue::Number(0) | LoopValue::Flag(false))
+    }
+}
+
+
+// contexts produced by lazy contexts during a render, kept until its end
+#[derive(Default)]
+pub(crate) struct Produced {
+    entry: OnceCell<(Box<dyn Context>, Box<Produced>)>,
+}
+
+// contexts produced so far in a render, by lazy context
+//
+// keys are wide pointers, the address alone being shared by a struct
+// and its first field
+struct Lazy<'a> {
+    next: &'a Produced,
+    produced: HashMap<*const (dyn Context + 'a), ContextRef<'a>>,
+}
+
+impl<'a> Lazy<'a> {
+    // the context produced by a lazy context, or the context itself
+    fn resolve(&mut self, context: ContextRef<'a>) -> ContextRef<'a> {
+        let key = context as *const (dyn Context + 'a);
+        if let Some(produced) = self.produced.get(&key) {
+            return *produced;
+        }
+        match context.lazy() {
+            Some(value) => {
+                let (value, next) = self.next.entry.get_or_init(|| (value, Box::default()));
+                self.next = next;
+                // a lazy context may produce another one
+                let value = self.resolve(value.as_ref());
+                self.produced.insert(key, value);
+                value
+            },
+            None => context
+        }
+    }
 }
 
 
 pub(crate) struct Stack<'a> {
     frames: Vec<Frame<'a>>,
+    lazy: Lazy<'a>,
 }
 
 impl<'a> Stack<'a> {
-    pub(crate) fn new(root: ContextRef<'a>) -> Self {
-        let frame = Frame::new_from_single(root);
+    pub(crate) fn new(root: ContextRef<'a>, produced: &'a Produced) -> Self {
+        let mut lazy = Lazy { next: produced, produced: HashMap::new() };
+        let frame = Frame::new_from_single(lazy.resolve(root));
         Stack {
-            frames: vec![frame]
+            frames: vec![frame],
+            lazy,
         }
     }
 
@@ -127,6 +246,19 @@ impl<'a> Stack<'a> {
         self.frames.len()
     }
 
+    pub(crate) fn push_context(&mut self, context: ContextRef<'a>) {
+        let context = self.lazy.resolve(context);
+        let frame = self.frame(context);
+        self.frames.push(frame);
+    }
+
+    fn frame(&mut self, context: ContextRef<'a>) -> Frame<'a> {
+        match context.children() {
+            Some(iterator) => Frame::new_from_iterator(context, iterator, &mut self.lazy),
+            None => Frame::new_from_single(context)
+        }
+    }
+
     pub(crate) fn truncate(&mut self, len: usize) {
         self.frames.truncate(len);
     }
@@ -142,7 +274,7 @@ impl<'a> Stack<'a> {
             let current = self.frames[idx].current().copied();
             if let Some(context) = current {
                 if let Some(iterator) = context.children() {
-                    let frame = Frame::new_from_iterator(iterator);
+                    let frame = Frame::new_from_iterator(context, iterator, &mut self.lazy);
                     self.frames.push(frame);
                 }
             };
@@ -159,11 +291,8 @@ impl<'a> Stack<'a> {
             }
 
         } else if let Some(context) = self.child(idx, name, is_dotted, location) {
-            let frame = if let Some(iterator) = context.children() {
-                Frame::new_from_iterator(iterator)
-            } else {
-                Frame::new_from_single(context)
-            };
+            let context = self.lazy.resolve(context);
+            let frame = self.frame(context);
             if is_dotted {
                 self.truncate(self.len() - 1);
             }
@@ -193,7 +322,7 @@ impl<'a> Stack<'a> {
         if is_dotted {
             idx += 1;
         }
-        self.frames[idx].current()?.child(name, location)
+        self.frames[idx].current().copied()?.child(name, location)
     }
 
 
@@ -212,7 +341,7 @@ impl<'a> Stack<'a> {
 
     pub(crate) fn next(&mut self) -> bool {
         if let Some(mut frame) = self.frames.pop() {
-            let more = frame.next();
+            let more = frame.next(&mut self.lazy);
             self.frames.push(frame);
             more
         } else {
@@ -221,12 +350,36 @@ impl<'a> Stack<'a> {
     }
 
     pub(crate) fn get(&mut self, name: &str) -> Option<ContextValue> {
+        match self.find(name) {
+            Some(context) => Some(context.value()),
+            None => self.computed(name).map(|context| context.value())
+        }
+    }
+
+    // context for a name, sequences included rather than their first item
+    pub(crate) fn context(&mut self, name: &str) -> Option<ContextRef<'a>> {
+        if name == "." {
+            self.current().copied()
+        } else {
+            let len = self.len();
+            if self.push(name, None) {
+                let result = self.frames.last().map(|frame| frame.context);
+                self.truncate(len);
+                result
+            } else {
+                None
+            }
+        }
+    }
+
+    // context for a name in interpolation position
+    pub(crate) fn find(&mut self, name: &str) -> Option<ContextRef<'a>> {
         if name == "." {
-            self.value()
+            self.current().copied()
         } else {
             let len = self.len();
             if self.push(name, None) {
-                let result = self.value();
+                let result = self.current().copied();
                 self.truncate(len);
                 result
             } else {
@@ -235,9 +388,58 @@ impl<'a> Stack<'a> {
         }
     }
 
+    // owned child computed for a name that does not resolve as a context
+    pub(crate) fn computed(&mut self, name: &str) -> Option<Box<dyn Context + 'a>> {
+        match name.rfind('.') {
+            Some(p) => {
+                let len = self.len();
+                let result = if self.push(&name[..p], None) {
+                    self.current().copied().and_then(|context| context.computed(&name[p + 1..]))
+                } else {
+                    None
+                };
+                self.truncate(len);
+                result
+            },
+            None => self.frames.iter().rev()
+                .find_map(|frame| frame.current().copied().and_then(|context| context.computed(name)))
+        }
+    }
+
+    // `@index`, `@index1`, `@first`, `@last` or `@length` of the innermost
+    // sequence being iterated, None for other names or outside sequences
+    pub(crate) fn loop_value(&mut self, name: &str) -> Option<LoopValue> {
+        let frame = self.frames.iter_mut().rev().find(|frame| frame.iterator.is_some())?;
+        let value = match name {
+            "@index" => LoopValue::Number(frame.index),
+            "@index1" => LoopValue::Number(frame.index + 1),
+            "@first" => LoopValue::Flag(frame.index == 0),
+            "@last" => LoopValue::Flag(frame.index + 1 == frame.length()),
+            "@length" => LoopValue::Number(frame.length()),
+            _ => return None
+        };
+        Some(value)
+    }
+
     pub fn value(&self) -> Option<ContextValue> {
         self.current().map(|context| context.value())
     }
+
+    // return the first part of a dotted name that cannot be resolved
+    pub(crate) fn missing_key(&mut self, name: &str) -> String {
+        let len = self.len();
+        let mut end = 0;
+        let key = loop {
+            let next = name[end..].find('.').map_or(name.len(), |p| end + p);
+            if next == name.len() || !self.push(&name[..next], None) {
+                break &name[end..next];
+            }
+            self.truncate(len);
+            end = next + 1;
+        };
+        self.truncate(len);
+        key.to_owned()
+    }
 }
 
 
@@ -249,7 +451,8 @@ mod test {
     #[test]
     fn basic_access() {
         let root = json1();
-        let mut stack = Stack::new(&root);
+        let produced = Produced::default();
+        let mut stack = Stack::new(&root, &produced);
 
         assert_eq!(stack.get("name"), sct("John Doe"));
         assert!(!stack.push("xxx", None));
@@ -267,7 +470,8 @@ mod test {
     #[test]
     fn normal_backtrack() {
         let root = json1();
-        let mut stack = Stack::new(&root);
+        let produced = Produced::default();
+        let mut stack = Stack::new(&root, &produced);
 
         stack.push("phones", None);
         assert!(stack.push("stuff", None));
@@ -281,7 +485,8 @@ mod test {
     #[test]
     fn dotted_from_top() {
         let root = json1();
-        let mut stack = Stack::new(&root);
+        let produced = Produced::default();
+        let mut stack = Stack::new(&root, &produced);
 
         assert!(stack.push("obj.part2", None));
         assert_eq!(stack.value(), sct("yyy"));
@@ -290,7 +495,8 @@ mod test {
     #[test]
     fn dotted_after_backtrack() {
         let root = json1();
-        let mut stack = Stack::new(&root);
+        let produced = Produced::default();
+        let mut stack = Stack::new(&root, &produced);
 
         stack.push("phones", None);
         assert!(stack.push("obj.part2", None));
@@ -300,7 +506,8 @@ mod test {
     #[test]
     fn backtrack_after_dotted() {
         let root = json1();
-        let mut stack = Stack::new(&root);
+        let produced = Produced::default();
+        let mut stack = Stack::new(&root, &produced);
 
         stack.push("phones", None);
         assert!(stack.push("obj.part2", None));
@@ -310,7 +517,8 @@ mod test {
     #[test]
     fn broken_chain() {
         let root = json1();
-        let mut stack = Stack::new(&root);
+        let produced = Produced::default();
+        let mut stack = Stack::new(&root, &produced);
 
         assert!(!stack.push("obj.part1.part2", None));
     }
@@ -318,7 +526,8 @@ mod test {
     #[test]
     fn failed_dotted_resolution_leaves_stack_unchanged() {
         let root = json1();
-        let mut stack = Stack::new(&root);
+        let produced = Produced::default();
+        let mut stack = Stack::new(&root, &produced);
 
         stack.push("name", None);
         assert!(!stack.push("obj.part1.part3", None));
@@ -331,7 +540,8 @@ mod test {
         
         // Test empty stack next() - should not panic
         let root = json1();
-        let mut stack = Stack::new(&root);
+        let produced = Produced::default();
+        let mut stack = Stack::new(&root, &produced);
         // Clear all frames to test empty stack
         stack.truncate(0);
         // This should return false, not panic
@@ -349,6 +559,38 @@ mod test {
         assert!(neg_zero.is_falsy());
     }
 
+    #[test]
+    fn missing_key_in_dotted_name() {
+        let root = json1();
+        let produced = Produced::default();
+        let mut stack = Stack::new(&root, &produced);
+
+        stack.push("phones", None);
+        assert_eq!(stack.missing_key("xxx"), "xxx");
+        assert_eq!(stack.missing_key("obj.part3"), "part3");
+        assert_eq!(stack.missing_key("obj.part1.part2"), "part2");
+        assert_eq!(stack.len(), 2);
+    }
+
+    #[test]
+    fn loop_values_of_innermost_sequence() {
+        let root = json1();
+        let produced = Produced::default();
+        let mut stack = Stack::new(&root, &produced);
+
+        assert_eq!(stack.loop_value("@index"), None);
+        stack.push("phones", None);
+        assert!(stack.next());
+        stack.push("prefix", None);
+        assert_eq!(stack.loop_value("@index"), Some(LoopValue::Number(1)));
+        assert_eq!(stack.loop_value("@first"), Some(LoopValue::Flag(false)));
+        assert_eq!(stack.loop_value("@last"), Some(LoopValue::Flag(true)));
+        assert_eq!(stack.loop_value("@length"), Some(LoopValue::Number(2)));
+        assert_eq!(stack.loop_value("@other"), None);
+        stack.push("stuff", None);
+        assert_eq!(stack.loop_value("@index1"), Some(LoopValue::Number(1)));
+    }
+
     fn json1() -> JsonValue {
         let data = r#"
         {
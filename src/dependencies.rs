@@ -0,0 +1,103 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Template, Reference, ReferenceKind};
+
+
+/// Partial dependencies between the templates of a
+/// [TemplateMap](crate::TemplateMap), see
+/// [TemplateMap::dependencies](crate::TemplateMap::dependencies).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dependencies {
+    /// Names of the partials and parents used by each template.
+    pub graph: BTreeMap<String, BTreeSet<String>>,
+    /// Partials and parents not found in the map, with the name of the
+    /// template using them.
+    pub missing: Vec<(String, Reference)>,
+    /// Dynamic partials and parents, which cannot be resolved statically,
+    /// with the name of the template using them.
+    pub dynamic: Vec<(String, Reference)>,
+    /// Groups of templates using each other, directly or not, names in
+    /// each group being sorted.
+    ///
+    /// A cycle renders forever unless it goes through a section that
+    /// eventually renders empty, as when rendering a tree.
+    pub cycles: Vec<Vec<String>>,
+}
+
+impl Dependencies {
+    /// True if there are no missing partials and no cycles.
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.cycles.is_empty()
+    }
+}
+
+
+pub(crate) fn dependencies<'a>(templates: impl Iterator<Item = (&'a String, &'a Template)>) -> Dependencies {
+    let mut result = Dependencies::default();
+    let templates = templates.collect::<BTreeMap<_, _>>();
+    for (&name, template) in &templates {
+        let mut used = BTreeSet::new();
+        for reference in template.references() {
+            match reference.kind {
+                ReferenceKind::Partial if templates.contains_key(&reference.name) => {
+                    used.insert(reference.name);
+                },
+                ReferenceKind::Partial => result.missing.push((name.clone(), reference)),
+                ReferenceKind::DynamicPartial => result.dynamic.push((name.clone(), reference)),
+                _ => {}
+            }
+        }
+        result.graph.insert(name.clone(), used);
+    }
+    result.cycles = cycles(&result.graph);
+    result
+}
+
+// strongly connected components with a cycle (Tarjan's algorithm)
+fn cycles(graph: &BTreeMap<String, BTreeSet<String>>) -> Vec<Vec<String>> {
+    struct State<'g> {
+        graph: &'g BTreeMap<String, BTreeSet<String>>,
+        index: BTreeMap<&'g str, (usize, usize)>,
+        stack: Vec<&'g str>,
+        cycles: Vec<Vec<String>>,
+    }
+
+    fn visit<'g>(state: &mut State<'g>, node: &'g str) {
+        let index = state.index.len();
+        state.index.insert(node, (index, index));
+        state.stack.push(node);
+        for next in &state.graph[node] {
+            let low = match state.index.get(next.as_str()) {
+                None => {
+                    visit(state, next);
+                    state.index[next.as_str()].1
+                },
+                Some(&(index, _)) if state.stack.contains(&next.as_str()) => index,
+                Some(_) => continue
+            };
+            let entry = state.index.get_mut(node).unwrap();
+            entry.1 = entry.1.min(low);
+        }
+        let (index, low) = state.index[node];
+        if index == low {
+            let start = state.stack.iter().rposition(|&n| n == node).unwrap();
+            let mut component = state.stack.split_off(start)
+                .into_iter()
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+            if component.len() > 1 || state.graph[node].contains(node) {
+                component.sort();
+                state.cycles.push(component);
+            }
+        }
+    }
+
+    let mut state = State { graph, index: BTreeMap::new(), stack: Vec::new(), cycles: Vec::new() };
+    for node in graph.keys() {
+        if !state.index.contains_key(node.as_str()) {
+            visit(&mut state, node);
+        }
+    }
+    state.cycles.sort();
+    state.cycles
+}
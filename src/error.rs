@@ -0,0 +1,236 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+
+/// Error produced when compiling a Mustache [Template](crate::Template).
+///
+/// The error locates the offending tag in the template source, giving
+/// the byte offset as well as the line and column (both starting at 1,
+/// the column counting characters) of the start of the tag.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TemplateError {
+    /// What went wrong.
+    pub kind: ErrorKind,
+    /// Byte offset of the offending tag in the template source.
+    pub offset: usize,
+    /// Line of the offending tag.
+    pub line: usize,
+    /// Column of the offending tag.
+    pub column: usize,
+    /// Source text of the offending tag, including delimiters.
+    pub tag: String,
+    /// Name of the template, if known (set by [TemplateMap](crate::TemplateMap)).
+    pub name: Option<String>,
+}
+
+impl TemplateError {
+    pub(crate) fn new(kind: ErrorKind, source: &str, span: (usize, usize)) -> Self {
+        let (start, end) = span;
+        let (line, column) = line_and_column(source, start);
+        TemplateError {
+            kind,
+            offset: start,
+            line,
+            column,
+            tag: source[start..end].to_owned(),
+            name: None,
+        }
+    }
+
+    pub(crate) fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "{}:", name)?;
+        }
+        write!(f, "{}:{}: {} `{}`", self.line, self.column, self.kind, self.tag)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+
+/// Category of a [TemplateError].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// A tag is opened but its close delimiter is never found.
+    MissingCloseDelimiter,
+    /// A set delimiters tag does not contain two valid delimiters.
+    InvalidDelimiters,
+    /// A close tag does not match the innermost open section.
+    UnexpectedEndSection,
+    /// A section, inverted section, block or parent is not closed.
+    /// The error is located at the opening tag.
+    UnclosedSection,
+    /// A filter chain in an interpolation tag is malformed
+    /// (see [CompileOptions::filters](crate::CompileOptions::filters)).
+    InvalidFilter,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ErrorKind::MissingCloseDelimiter => "missing close delimiter",
+            ErrorKind::InvalidDelimiters => "invalid delimiters tag",
+            ErrorKind::UnexpectedEndSection => "unexpected end of section",
+            ErrorKind::UnclosedSection => "unclosed section",
+            ErrorKind::InvalidFilter => "invalid filter",
+        };
+        f.write_str(text)
+    }
+}
+
+
+/// Error produced when rendering a [Template](crate::Template) with
+/// [RenderOptions](crate::RenderOptions).
+/// 
+/// Except for [RenderErrorKind::Write] and [RenderErrorKind::OutputLimit],
+/// the error locates the tag that failed to render in the source of the
+/// template or partial being rendered, with the same conventions as [TemplateError].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderError {
+    /// What went wrong.
+    pub kind: RenderErrorKind,
+    /// Name in the tag, as a dotted path if so written.
+    pub name: String,
+    /// Part of the name that could not be resolved.
+    pub key: String,
+    /// Name of the partial being rendered, None for the rendered template.
+    pub template: Option<String>,
+    /// Byte offset of the failing tag.
+    pub offset: usize,
+    /// Line of the failing tag.
+    pub line: usize,
+    /// Column of the failing tag.
+    pub column: usize,
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if matches!(self.kind, RenderErrorKind::Write | RenderErrorKind::OutputLimit) {
+            return write!(f, "{}", self.kind);
+        }
+        if let Some(template) = &self.template {
+            write!(f, "{}:", template)?;
+        }
+        write!(f, "{}:{}: {} `{}`", self.line, self.column, self.kind, self.key)?;
+        if self.key != self.name {
+            write!(f, " in `{}`", self.name)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<fmt::Error> for RenderError {
+    fn from(_: fmt::Error) -> Self {
+        RenderError {
+            kind: RenderErrorKind::Write,
+            name: String::new(),
+            key: String::new(),
+            template: None,
+            offset: 0,
+            line: 0,
+            column: 0,
+        }
+    }
+}
+
+
+/// Category of a [RenderError].
+#[derive(Clone, Debug, PartialEq)]
+pub enum RenderErrorKind {
+    /// A name in interpolation position is not found (strict mode).
+    MissingVariable,
+    /// A name in section or inverted section position is not found (strict mode).
+    MissingSection,
+    /// A partial is not found in the template store (strict mode).
+    MissingPartial,
+    /// The output failed.
+    Write,
+    /// Partials or lambda results are nested deeper than
+    /// [RenderOptions::max_depth](crate::RenderOptions::max_depth).
+    DepthLimit,
+    /// The output is larger than
+    /// [RenderOptions::max_output](crate::RenderOptions::max_output).
+    OutputLimit,
+    /// Sections iterated more than
+    /// [RenderOptions::max_iterations](crate::RenderOptions::max_iterations).
+    IterationLimit,
+    /// A filter is neither in
+    /// [RenderOptions::filters](crate::RenderOptions::filters) nor built in (strict mode).
+    UnknownFilter,
+}
+
+impl fmt::Display for RenderErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            RenderErrorKind::MissingVariable => "missing variable",
+            RenderErrorKind::MissingSection => "missing section",
+            RenderErrorKind::MissingPartial => "missing partial",
+            RenderErrorKind::Write => "output error",
+            RenderErrorKind::DepthLimit => "nesting depth limit exceeded",
+            RenderErrorKind::OutputLimit => "output size limit exceeded",
+            RenderErrorKind::IterationLimit => "iteration limit exceeded",
+            RenderErrorKind::UnknownFilter => "unknown filter",
+        };
+        f.write_str(text)
+    }
+}
+
+
+/// Error produced when loading a template from a
+/// [FileSystemStore](crate::FileSystemStore).
+#[derive(Debug)]
+pub enum StoreError {
+    /// The file could not be read.
+    Io(PathBuf, io::Error),
+    /// The file is not a valid template, the [TemplateError] being
+    /// named after the file.
+    Template(PathBuf, TemplateError),
+}
+
+impl StoreError {
+    /// Path of the file that failed to load.
+    pub fn path(&self) -> &Path {
+        match self {
+            StoreError::Io(path, _) => path,
+            StoreError::Template(path, _) => path,
+        }
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            StoreError::Template(_, err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StoreError::Io(_, err) => Some(err),
+            StoreError::Template(_, err) => Some(err),
+        }
+    }
+}
+
+
+// return 1-based line and column (in characters) of a byte offset in source
+pub(crate) fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let start_of_line = before.rfind('\n').map_or(0, |p| p + 1);
+    let column = before[start_of_line..].chars().count() + 1;
+    (line, column)
+}
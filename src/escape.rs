@@ -0,0 +1,204 @@
+use std::fmt::{self, Write};
+
+
+/// Escaping strategy for interpolated values.
+///
+/// The escaper is applied to the text of `{{name}}` tags, while `{{{name}}}`
+/// and `{{&name}}` are always rendered as is. It is selected when rendering
+/// with [RenderOptions](crate::RenderOptions), defaulting to [HtmlEscaper].
+///
+/// # Sample
+///
+/// ```
+/// use mustache::{Template, RenderOptions, LatexEscaper};
+/// use serde_json::json;
+///
+/// let template = Template::from(r"\textbf{ {{name}} } {{{raw}}}").unwrap();
+/// let data = json!({ "name": "R&D_2", "raw": "&" });
+/// let options = RenderOptions { escaper: &LatexEscaper, ..Default::default() };
+///
+/// let result = template.render_with_options(&data, None, &options).unwrap();
+/// assert_eq!(result, r"\textbf{ R\&D\_2 } &");
+/// ```
+pub trait Escaper {
+    /// Write the escaped form of `text` to `out`.
+    fn escape(&self, text: &str, out: &mut dyn Write) -> fmt::Result;
+}
+
+
+/// HTML escaping, the Mustache default.
+///
+/// Besides `&<>"'`, the characters `/`, `=` and backtick are escaped.
+pub struct HtmlEscaper;
+
+impl Escaper for HtmlEscaper {
+    fn escape(&self, text: &str, out: &mut dyn Write) -> fmt::Result {
+        escape_chars(text, out, |c| match c {
+            '&' => Some("&amp;"),
+            '<' => Some("&lt;"),
+            '>' => Some("&gt;"),
+            '"' => Some("&quot;"),
+            '\'' => Some("&#39;"),
+            '/' => Some("&#47;"),
+            '=' => Some("&#61;"),
+            '`' => Some("&#96;"),
+            _ => None
+        })
+    }
+}
+
+/// XML escaping of the five predefined entities.
+pub struct XmlEscaper;
+
+impl Escaper for XmlEscaper {
+    fn escape(&self, text: &str, out: &mut dyn Write) -> fmt::Result {
+        escape_chars(text, out, |c| match c {
+            '&' => Some("&amp;"),
+            '<' => Some("&lt;"),
+            '>' => Some("&gt;"),
+            '"' => Some("&quot;"),
+            '\'' => Some("&apos;"),
+            _ => None
+        })
+    }
+}
+
+/// JSON string escaping.
+///
+/// The result is the content of a JSON string: the template is expected
+/// to provide the surrounding quotes, as in `"name": "{{name}}"`.
+pub struct JsonEscaper;
+
+impl Escaper for JsonEscaper {
+    fn escape(&self, text: &str, out: &mut dyn Write) -> fmt::Result {
+        let mut start = 0;
+        for (idx, c) in text.char_indices() {
+            let escaped = match c {
+                '"' => "\\\"",
+                '\\' => "\\\\",
+                '\n' => "\\n",
+                '\r' => "\\r",
+                '\t' => "\\t",
+                '\u{8}' => "\\b",
+                '\u{c}' => "\\f",
+                c if c < ' ' => {
+                    out.write_str(&text[start..idx])?;
+                    write!(out, "\\u{:04x}", c as u32)?;
+                    start = idx + 1;
+                    continue;
+                },
+                _ => continue
+            };
+            out.write_str(&text[start..idx])?;
+            out.write_str(escaped)?;
+            start = idx + 1;
+        }
+        out.write_str(&text[start..])
+    }
+}
+
+/// LaTeX escaping of special characters in text mode.
+pub struct LatexEscaper;
+
+impl Escaper for LatexEscaper {
+    fn escape(&self, text: &str, out: &mut dyn Write) -> fmt::Result {
+        escape_chars(text, out, |c| match c {
+            '\\' => Some("\\textbackslash{}"),
+            '{' => Some("\\{"),
+            '}' => Some("\\}"),
+            '$' => Some("\\$"),
+            '&' => Some("\\&"),
+            '#' => Some("\\#"),
+            '%' => Some("\\%"),
+            '_' => Some("\\_"),
+            '^' => Some("\\textasciicircum{}"),
+            '~' => Some("\\textasciitilde{}"),
+            _ => None
+        })
+    }
+}
+
+/// POSIX shell escaping.
+///
+/// The value is rendered as a single word: it is enclosed in single quotes,
+/// with embedded single quotes written as `'\''`.
+pub struct ShellEscaper;
+
+impl Escaper for ShellEscaper {
+    fn escape(&self, text: &str, out: &mut dyn Write) -> fmt::Result {
+        out.write_char('\'')?;
+        escape_chars(text, out, |c| match c {
+            '\'' => Some("'\\''"),
+            _ => None
+        })?;
+        out.write_char('\'')
+    }
+}
+
+/// No escaping: `{{name}}` renders as `{{{name}}}`.
+pub struct NoEscaper;
+
+impl Escaper for NoEscaper {
+    fn escape(&self, text: &str, out: &mut dyn Write) -> fmt::Result {
+        out.write_str(text)
+    }
+}
+
+
+// write text to out, replacing characters for which escaped returns a value
+fn escape_chars(
+    text: &str, out: &mut dyn Write, escaped: impl Fn(char) -> Option<&'static str>
+) -> fmt::Result {
+    let mut start = 0;
+    for (idx, c) in text.char_indices() {
+        if let Some(replacement) = escaped(c) {
+            out.write_str(&text[start..idx])?;
+            out.write_str(replacement)?;
+            start = idx + c.len_utf8();
+        }
+    }
+    out.write_str(&text[start..])
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html() {
+        assert_eq!(escaped(&HtmlEscaper, "a<b>&'c'=`/`"), "a&lt;b&gt;&amp;&#39;c&#39;&#61;&#96;&#47;&#96;");
+    }
+
+    #[test]
+    fn xml() {
+        assert_eq!(escaped(&XmlEscaper, "<a b='c'>&\"/"), "&lt;a b=&apos;c&apos;&gt;&amp;&quot;/");
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(escaped(&JsonEscaper, "a\"b\\c\nd\u{1}é"), "a\\\"b\\\\c\\nd\\u0001é");
+    }
+
+    #[test]
+    fn latex() {
+        assert_eq!(escaped(&LatexEscaper, "50% of $x_1^2 {~}"), "50\\% of \\$x\\_1\\textasciicircum{}2 \\{\\textasciitilde{}\\}");
+    }
+
+    #[test]
+    fn shell() {
+        assert_eq!(escaped(&ShellEscaper, "it's $HOME"), "'it'\\''s $HOME'");
+        assert_eq!(escaped(&ShellEscaper, ""), "''");
+    }
+
+    #[test]
+    fn none() {
+        assert_eq!(escaped(&NoEscaper, "<&>"), "<&>");
+    }
+
+    fn escaped(escaper: &dyn Escaper, text: &str) -> String {
+        let mut result = String::new();
+        escaper.escape(text, &mut result).unwrap();
+        result
+    }
+}
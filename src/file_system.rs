@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use crate::{Template, CompileOptions, TemplateStore, StoreError};
+
+
+/// [TemplateStore] loading templates from files.
+///
+/// Templates are found by scanning one or more root directories for files
+/// with a given extension when the store is created. The name of a template
+/// is its path relative to its root, without extension and using `/` as
+/// separator, so that `{{>layouts/base}}` resolves to `layouts/base.mustache`.
+/// When a name is found under several roots, the first root wins.
+/// Extensions may contain dots, as in `html.mustache`. Symbolic links to
+/// files are followed, symbolic links to directories are not.
+///
+/// Files are read and compiled on first use, and cached for the lifetime
+/// of the store. A file that cannot be read or compiled renders as a
+/// missing partial, the error being available from [FileSystemStore::load].
+///
+/// # Sample
+///
+/// ```no_run
+/// use mustache::{Template, FileSystemStore, JsonValue};
+///
+/// let partials = FileSystemStore::new(&["templates"], "mustache").unwrap();
+/// let template = Template::from("{{>layouts/base}}").unwrap();
+/// let data = serde_json::from_str::<JsonValue>(r#"{"title": "home"}"#).unwrap();
+///
+/// let result = template.render_with_partials(&data, &partials);
+/// ```
+pub struct FileSystemStore {
+    options: CompileOptions,
+    entries: HashMap<String, Entry>,
+}
+
+struct Entry {
+    path: PathBuf,
+    template: OnceLock<Result<Arc<Template>, StoreError>>,
+}
+
+impl FileSystemStore {
+    /// Create a [FileSystemStore] for files with `extension` (such as
+    /// `mustache` or `.hbs`) under `roots`.
+    ///
+    /// Fails if a root directory cannot be read.
+    pub fn new<P: AsRef<Path>>(roots: &[P], extension: &str) -> io::Result<Self> {
+        FileSystemStore::compile(roots, extension, &CompileOptions::default())
+    }
+
+    /// Create a [FileSystemStore] compiling files using [CompileOptions].
+    pub fn compile<P: AsRef<Path>>(
+        roots: &[P], extension: &str, options: &CompileOptions
+    ) -> io::Result<Self> {
+        let extension = extension.trim_start_matches('.');
+        let mut entries = HashMap::new();
+        for root in roots {
+            let mut files = Vec::new();
+            scan(root.as_ref(), extension, &mut files)?;
+            for path in files {
+                let name = template_name(root.as_ref(), &path, extension);
+                entries.entry(name).or_insert_with(|| Entry { path, template: OnceLock::new() });
+            }
+        }
+        Ok(FileSystemStore { options: *options, entries })
+    }
+
+    /// Names of the templates found in the store.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// Path of the file for a template name.
+    pub fn path(&self, name: &str) -> Option<&Path> {
+        self.entries.get(name).map(|entry| entry.path.as_path())
+    }
+
+    /// Get a template, compiling it if not done yet.
+    ///
+    /// Return [Result::Ok] with [None] if there is no such template, and
+    /// a [StoreError] naming the file if it cannot be read or compiled.
+    pub fn load(&self, name: &str) -> Result<Option<Arc<Template>>, &StoreError> {
+        match self.entries.get(name) {
+            Some(entry) => entry.load(&self.options).as_ref().map(|template| Some(template.clone())),
+            None => Ok(None)
+        }
+    }
+
+    /// Compile all templates, returning the errors for files that fail.
+    pub fn load_all(&self) -> Vec<&StoreError> {
+        let mut errors = self.entries.values()
+            .filter_map(|entry| entry.load(&self.options).as_ref().err())
+            .collect::<Vec<_>>();
+        errors.sort_by(|a, b| a.path().cmp(b.path()));
+        errors
+    }
+}
+
+impl Entry {
+    fn load(&self, options: &CompileOptions) -> &Result<Arc<Template>, StoreError> {
+        self.template.get_or_init(|| compile_file(&self.path, options).map(Arc::new))
+    }
+}
+
+impl TemplateStore for FileSystemStore {
+    fn get(&self, name: &str) -> Option<Arc<Template>> {
+        self.load(name).ok().flatten()
+    }
+}
+
+
+/// [TemplateStore] reloading templates from files when they change.
+///
+/// This store is intended for development: on each access to a template,
+/// the modification time of its file is checked and the file recompiled
+/// if it changed since it was last loaded. If the new version does not
+/// compile, the last good version is used and the error is available from
+/// [HotReloadStore::error] until the file is fixed.
+///
+/// Names are resolved as in [FileSystemStore], except that files are looked
+/// up on each access rather than scanned when the store is created, so that
+/// new files are found. A template whose file is removed is no longer found.
+pub struct HotReloadStore {
+    roots: Vec<PathBuf>,
+    extension: String,
+    options: CompileOptions,
+    entries: Mutex<HashMap<String, ReloadEntry>>,
+}
+
+struct ReloadEntry {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    template: Option<Arc<Template>>,
+    error: Option<Arc<StoreError>>,
+}
+
+impl HotReloadStore {
+    /// Create a [HotReloadStore] for files with `extension` under `roots`.
+    pub fn new<P: AsRef<Path>>(roots: &[P], extension: &str) -> Self {
+        HotReloadStore::compile(roots, extension, &CompileOptions::default())
+    }
+
+    /// Create a [HotReloadStore] compiling files using [CompileOptions].
+    pub fn compile<P: AsRef<Path>>(roots: &[P], extension: &str, options: &CompileOptions) -> Self {
+        HotReloadStore {
+            roots: roots.iter().map(|root| root.as_ref().to_owned()).collect(),
+            extension: extension.trim_start_matches('.').to_owned(),
+            options: *options,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Error from the last attempt to load a template, if it failed.
+    ///
+    /// This is None once the template loads again.
+    pub fn error(&self, name: &str) -> Option<Arc<StoreError>> {
+        self.refresh(name);
+        let entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        entries.get(name).and_then(|entry| entry.error.clone())
+    }
+
+    // reload the template if its file changed, returning the current version
+    fn refresh(&self, name: &str) -> Option<Arc<Template>> {
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        let Some(path) = self.resolve(name) else {
+            entries.remove(name);
+            return None;
+        };
+        let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+        let is_new = !entries.contains_key(name);
+        let entry = entries.entry(name.to_owned()).or_insert_with(|| ReloadEntry {
+            path: path.clone(),
+            modified: None,
+            template: None,
+            error: None,
+        });
+        if is_new || entry.path != path || entry.modified != modified {
+            entry.path = path;
+            entry.modified = modified;
+            match compile_file(&entry.path, &self.options) {
+                Ok(template) => {
+                    entry.template = Some(Arc::new(template));
+                    entry.error = None;
+                },
+                Err(err) => entry.error = Some(Arc::new(err)),
+            }
+        }
+        entry.template.clone()
+    }
+
+    // path of the file for a name in the first root where it exists
+    fn resolve(&self, name: &str) -> Option<PathBuf> {
+        let relative = Path::new(name);
+        if !relative.components().all(|part| matches!(part, Component::Normal(_))) {
+            return None;
+        }
+        let file = format!("{}.{}", name, self.extension);
+        self.roots.iter()
+            .map(|root| root.join(&file))
+            .find(|path| path.is_file())
+    }
+}
+
+impl TemplateStore for HotReloadStore {
+    fn get(&self, name: &str) -> Option<Arc<Template>> {
+        self.refresh(name)
+    }
+}
+
+
+fn compile_file(path: &Path, options: &CompileOptions) -> Result<Template, StoreError> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| StoreError::Io(path.to_owned(), err))?;
+    Template::compile(&text, options).map_err(|err| {
+        let name = path.to_string_lossy();
+        StoreError::Template(path.to_owned(), err.with_name(&name))
+    })
+}
+
+
+// collect files with extension under dir, recursively, without following
+// symbolic links to directories which may loop
+fn scan(dir: &Path, extension: &str, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let suffix = format!(".{}", extension);
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            scan(&path, extension, files)?;
+        } else if path.is_file() && entry.file_name().to_str()
+            .is_some_and(|name| name.len() > suffix.len() && name.ends_with(&suffix)) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// relative path from root without extension, with '/' separators
+fn template_name(root: &Path, path: &Path, extension: &str) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let parts = relative.components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+    let name = parts.join("/");
+    name[..name.len() - extension.len() - 1].to_owned()
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ErrorKind;
+
+
+/// Filter applied to an interpolated value, as in `{{name | truncate:20}}`.
+///
+/// See [CompileOptions::filters](crate::CompileOptions::filters).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    name: String,
+    args: Vec<String>,
+}
+
+impl Filter {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Arguments following the name, separated by `:`.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+}
+
+
+type FilterFn = dyn Fn(&str, &[&str]) -> String + Send + Sync;
+
+/// Named functions transforming interpolated values, see
+/// [CompileOptions::filters](crate::CompileOptions::filters).
+///
+/// A filter receives the value (before escaping) and the arguments written
+/// after its name. Filters in the registry hide built-in filters with the
+/// same names:
+///
+/// - `upper`, `lower`: change the case of the value.
+/// - `capitalize`: upper case the first character.
+/// - `trim`: remove leading and trailing whitespace.
+/// - `truncate:N[:SUFFIX]`: keep the first N characters, appending SUFFIX
+///   (none by default) if the value is longer.
+/// - `default:TEXT`: replace an empty value.
+/// - `replace:FROM:TO`: replace all occurrences of FROM.
+/// - `fixed:N`: format a number with N decimals.
+///
+/// Built-in filters with invalid arguments leave the value unchanged.
+///
+/// # Sample
+///
+/// ```
+/// use mustache::{Template, CompileOptions, RenderOptions, Filters};
+/// use serde_json::json;
+///
+/// let mut filters = Filters::new();
+/// filters.add("currency", |value, args| format!("{}{}", args.first().unwrap_or(&"$"), value));
+///
+/// let options = CompileOptions { filters: true, ..Default::default() };
+/// let template = Template::compile("{{name | upper}}: {{price | fixed:2 | currency:€}}", &options).unwrap();
+/// let data = json!({ "name": "tea", "price": 3.5 });
+/// let options = RenderOptions { filters: Some(&filters), ..Default::default() };
+/// assert_eq!(template.render_with_options(&data, None, &options).unwrap(), "TEA: €3.50");
+/// ```
+#[derive(Default)]
+pub struct Filters {
+    filters: HashMap<String, Box<FilterFn>>,
+}
+
+impl Filters {
+    pub fn new() -> Self {
+        Filters::default()
+    }
+
+    /// Add a filter, replacing any filter with the same name.
+    pub fn add<T>(&mut self, name: &str, fun: T) -> &mut Self
+    where T: Fn(&str, &[&str]) -> String + Send + Sync + 'static {
+        self.filters.insert(name.to_owned(), Box::new(fun));
+        self
+    }
+
+    /// Whether a filter is in the registry or built in.
+    pub fn contains(&self, name: &str) -> bool {
+        self.filters.contains_key(name) || builtin(name).is_some()
+    }
+}
+
+impl fmt::Debug for Filters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names = self.filters.keys().collect::<Vec<_>>();
+        names.sort();
+        f.debug_struct("Filters").field("filters", &names).finish()
+    }
+}
+
+
+// result of a filter from the registry or built in, None if unknown
+pub(crate) fn apply(filters: Option<&Filters>, filter: &Filter, value: &str) -> Option<String> {
+    let args = filter.args.iter().map(String::as_str).collect::<Vec<_>>();
+    match filters.and_then(|filters| filters.filters.get(&filter.name)) {
+        Some(fun) => Some(fun(value, &args)),
+        None => builtin(&filter.name).map(|fun| fun(value, &args))
+    }
+}
+
+fn builtin(name: &str) -> Option<fn(&str, &[&str]) -> String> {
+    let fun: fn(&str, &[&str]) -> String = match name {
+        "upper" => |value, _| value.to_uppercase(),
+        "lower" => |value, _| value.to_lowercase(),
+        "capitalize" => |value, _| {
+            let mut chars = value.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new()
+            }
+        },
+        "trim" => |value, _| value.trim().to_owned(),
+        "truncate" => |value, args| match args.first().map(|len| len.parse::<usize>()) {
+            Some(Ok(len)) => match value.char_indices().nth(len) {
+                Some((end, _)) => value[..end].to_owned() + args.get(1).unwrap_or(&""),
+                None => value.to_owned()
+            },
+            _ => value.to_owned()
+        },
+        "default" => |value, args| match (value.is_empty(), args.first()) {
+            (true, Some(text)) => (*text).to_owned(),
+            _ => value.to_owned()
+        },
+        "replace" => |value, args| match args {
+            [from, to] if !from.is_empty() => value.replace(from, to),
+            _ => value.to_owned()
+        },
+        "fixed" => |value, args| {
+            let decimals = args.first().and_then(|decimals| decimals.parse::<usize>().ok());
+            match (value.trim().parse::<f64>(), decimals) {
+                (Ok(number), Some(decimals)) => format!("{:.*}", decimals, number),
+                _ => value.to_owned()
+            }
+        },
+        _ => return None
+    };
+    Some(fun)
+}
+
+
+// split `name | filter:arg:"quoted arg" | ...` into the name and filters
+pub(crate) fn parse(text: &str) -> Result<(&str, Vec<Filter>), ErrorKind> {
+    let mut parts = split_unquoted(text, '|')?.into_iter();
+    let name = parts.next().unwrap_or("").trim();
+    if name.is_empty() {
+        return Err(ErrorKind::InvalidFilter);
+    }
+    let filters = parts
+        .map(|part| {
+            let mut words = split_unquoted(part, ':')?.into_iter();
+            let name = words.next().unwrap_or("").trim();
+            if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '"') {
+                return Err(ErrorKind::InvalidFilter);
+            }
+            Ok(Filter { name: name.to_owned(), args: words.map(unquote).collect() })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((name, filters))
+}
+
+fn split_unquoted(text: &str, separator: char) -> Result<Vec<&str>, ErrorKind> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (p, c) in text.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            parts.push(&text[start..p]);
+            start = p + c.len_utf8();
+        }
+    }
+    if quoted {
+        return Err(ErrorKind::InvalidFilter);
+    }
+    parts.push(&text[start..]);
+    Ok(parts)
+}
+
+// trimmed word, keeping whitespace within double quotes
+fn unquote(word: &str) -> String {
+    let word = word.trim();
+    match word.strip_prefix('"').and_then(|word| word.strip_suffix('"')) {
+        Some(quoted) => quoted.to_owned(),
+        None => word.to_owned()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(name: &str, args: &[&str]) -> Filter {
+        Filter { name: name.to_owned(), args: args.iter().map(|arg| arg.to_string()).collect() }
+    }
+
+    #[test]
+    fn parse_chains() {
+        assert_eq!(parse("a.b | upper"), Ok(("a.b", vec![filter("upper", &[])])));
+        assert_eq!(
+            parse(r#"name|truncate: 3 :"..."| default:" - " |replace:"a:b":"#),
+            Ok(("name", vec![
+                filter("truncate", &["3", "..."]),
+                filter("default", &[" - "]),
+                filter("replace", &["a:b", ""]),
+            ]))
+        );
+        assert_eq!(parse(r#"x | default:"|""#), Ok(("x", vec![filter("default", &["|"])])));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for text in ["| upper", "x |", "x | | upper", "x | up per", r#"x | default:"a"#, r#"x | "upper""#] {
+            assert_eq!(parse(text), Err(ErrorKind::InvalidFilter), "{}", text);
+        }
+    }
+
+    #[test]
+    fn builtins() {
+        let apply = |name: &str, args: &[&str], value: &str| {
+            apply(None, &filter(name, args), value)
+        };
+        assert_eq!(apply("upper", &[], "aé"), Some("AÉ".to_owned()));
+        assert_eq!(apply("capitalize", &[], "éa b"), Some("Éa b".to_owned()));
+        assert_eq!(apply("truncate", &["2", "…"], "abc"), Some("ab…".to_owned()));
+        assert_eq!(apply("truncate", &["3", "…"], "abc"), Some("abc".to_owned()));
+        assert_eq!(apply("truncate", &["x"], "abc"), Some("abc".to_owned()));
+        assert_eq!(apply("default", &["none"], ""), Some("none".to_owned()));
+        assert_eq!(apply("replace", &["a", "o"], "banana"), Some("bonono".to_owned()));
+        assert_eq!(apply("fixed", &["1"], "2.26"), Some("2.3".to_owned()));
+        assert_eq!(apply("fixed", &["1"], "n/a"), Some("n/a".to_owned()));
+        assert_eq!(apply("missing", &[], "x"), None);
+    }
+}
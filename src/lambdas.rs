@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use crate::{Context, ContextValue, ContextRef, ContextRefIterator, RenderError};
+use crate::context::Stack;
+use crate::template::{Renderer, Segments};
+
+
+/// Function called when rendering, see [Lambdas].
+pub struct Lambda(LambdaFn);
+
+enum LambdaFn {
+    Arity0(Box<dyn Fn() -> String>),
+    Arity1(Box<dyn Fn(&str) -> String>),
+    Helper(Box<HelperFn>),
+}
+
+pub(crate) type HelperFn = dyn Fn(&mut LambdaHelper) -> Result<(), RenderError>;
+
+impl Lambda {
+    /// Lambda taking no argument.
+    pub fn new0<T>(fun: T) -> Lambda
+    where T: Fn() -> String + 'static {
+        Lambda(LambdaFn::Arity0(Box::new(fun)))
+    }
+
+    /// Lambda taking the unprocessed content of the section it is used in,
+    /// and an empty string in interpolation position.
+    pub fn new1<T>(fun: T) -> Lambda
+    where T: Fn(&str) -> String + 'static {
+        Lambda(LambdaFn::Arity1(Box::new(fun)))
+    }
+
+    /// Lambda writing its result through a [LambdaHelper], which is not
+    /// parsed as a template.
+    pub fn helper<T>(fun: T) -> Lambda
+    where T: Fn(&mut LambdaHelper) -> Result<(), RenderError> + 'static {
+        Lambda(LambdaFn::Helper(Box::new(fun)))
+    }
+
+    // result of the lambda, a template to render (empty for helpers)
+    pub(crate) fn call(&self, section: Option<&str>) -> String {
+        match &self.0 {
+            LambdaFn::Arity0(fun) => fun(),
+            LambdaFn::Arity1(fun) => fun(section.unwrap_or("")),
+            LambdaFn::Helper(_) => String::new(),
+        }
+    }
+
+    pub(crate) fn helper_fn(&self) -> Option<&HelperFn> {
+        match &self.0 {
+            LambdaFn::Helper(fun) => Some(fun.as_ref()),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Debug for Lambda {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            LambdaFn::Arity0(_) => f.write_str("Lambda(0)"),
+            LambdaFn::Arity1(_) => f.write_str("Lambda(1)"),
+            LambdaFn::Helper(_) => f.write_str("Lambda(helper)"),
+        }
+    }
+}
+
+// a lambda is a truthy context with no content of its own
+impl Context for Lambda {
+    fn child(&self, _name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        None
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        None
+    }
+
+    fn value(&self) -> ContextValue {
+        ContextValue::Text("".to_owned())
+    }
+
+    fn is_falsy(&self) -> bool {
+        false
+    }
+
+    fn lambda(&self) -> Option<&Lambda> {
+        Some(self)
+    }
+}
+
+
+/// Named functions attached to a render.
+///
+/// The lambdas are exposed as names of the root context, hiding data with
+/// the same names, so that any [Context] (such as [JsonValue](crate::JsonValue))
+/// can use lambdas as defined by the optional lambdas module of the
+/// Mustache specification.
+///
+/// In interpolation position, the result of a lambda is rendered with the
+/// default delimiters and interpolated. In section position, a lambda
+/// receives the unprocessed content of the section, and the result is
+/// rendered with the current delimiters in place of the section.
+/// Lambdas are called each time they are used.
+///
+/// # Sample
+///
+/// ```
+/// use mustache::{Template, Lambdas};
+/// use serde_json::json;
+///
+/// let mut lambdas = Lambdas::new();
+/// lambdas.lambda0("today", || "{{year}}-01-01".to_owned());
+/// lambdas.lambda1("bold", |text| format!("<b>{}</b>", text));
+///
+/// let data = json!({ "name": "x", "year": 2024 });
+/// let template = Template::from("{{#bold}}{{name}}{{/bold}} {{today}}").unwrap();
+/// assert_eq!(template.render(&lambdas.overlay(&data)), "<b>x</b> 2024-01-01");
+/// ```
+#[derive(Debug, Default)]
+pub struct Lambdas {
+    lambdas: HashMap<String, Lambda>,
+}
+
+impl Lambdas {
+    pub fn new() -> Self {
+        Lambdas::default()
+    }
+
+    /// Add a lambda taking no argument, replacing any lambda with the same name.
+    pub fn lambda0<T>(&mut self, name: &str, fun: T) -> &mut Self
+    where T: Fn() -> String + 'static {
+        self.insert(name, Lambda::new0(fun))
+    }
+
+    /// Add a lambda taking the content of a section, replacing any lambda
+    /// with the same name.
+    pub fn lambda1<T>(&mut self, name: &str, fun: T) -> &mut Self
+    where T: Fn(&str) -> String + 'static {
+        self.insert(name, Lambda::new1(fun))
+    }
+
+    /// Add a lambda using a [LambdaHelper], replacing any lambda with the
+    /// same name.
+    pub fn helper<T>(&mut self, name: &str, fun: T) -> &mut Self
+    where T: Fn(&mut LambdaHelper) -> Result<(), RenderError> + 'static {
+        self.insert(name, Lambda::helper(fun))
+    }
+
+    /// Add a [Lambda], replacing any lambda with the same name.
+    pub fn insert(&mut self, name: &str, lambda: Lambda) -> &mut Self {
+        self.lambdas.insert(name.to_owned(), lambda);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Lambda> {
+        self.lambdas.get(name)
+    }
+
+    /// Context with the lambdas on top of `root`.
+    pub fn overlay<'a>(&'a self, root: ContextRef<'a>) -> LambdasOverlay<'a> {
+        LambdasOverlay { lambdas: self, root }
+    }
+}
+
+
+/// Root [Context] with [Lambdas], see [Lambdas::overlay].
+pub struct LambdasOverlay<'a> {
+    lambdas: &'a Lambdas,
+    root: ContextRef<'a>,
+}
+
+impl Context for LambdasOverlay<'_> {
+    fn child(&self, name: &str, section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        match self.lambdas.get(name) {
+            Some(lambda) => Some(lambda),
+            None => self.root.child(name, section)
+        }
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        self.root.children()
+    }
+
+    fn value(&self) -> ContextValue {
+        self.root.value()
+    }
+
+    fn is_falsy(&self) -> bool {
+        self.root.is_falsy()
+    }
+
+    fn lambda(&self) -> Option<&Lambda> {
+        self.root.lambda()
+    }
+
+    fn computed(&self, name: &str) -> Option<Box<dyn Context + '_>> {
+        self.root.computed(name)
+    }
+}
+
+
+/// Access to the render from a lambda created by [Lambda::helper].
+///
+/// Unlike other lambdas, a helper writes its result directly: text is not
+/// parsed as a template, and the content of the section where the lambda
+/// is used is rendered as compiled with the template, as often as needed.
+///
+/// # Sample
+///
+/// ```
+/// use mustache::{Template, Lambdas, ContextValue};
+/// use serde_json::json;
+///
+/// // render the section for each item of `items`, sorted by name,
+/// // with separators
+/// let mut lambdas = Lambdas::new();
+/// lambdas.helper("sorted", |helper| {
+///     let mut items = match helper.context("items").and_then(|items| items.children()) {
+///         Some(items) => items.collect::<Vec<_>>(),
+///         None => return Ok(())
+///     };
+///     items.sort_by_key(|item| match item.child("name", None).map(|name| name.value()) {
+///         Some(ContextValue::Text(name)) => name,
+///         _ => String::new()
+///     });
+///     for (index, item) in items.into_iter().enumerate() {
+///         if index > 0 {
+///             helper.write(", ")?;
+///         }
+///         helper.render_with(item)?;
+///     }
+///     Ok(())
+/// });
+///
+/// let data = json!({ "items": [{ "name": "b" }, { "name": "c" }, { "name": "a" }] });
+/// let template = Template::from("{{#sorted}}<{{name}}>{{/sorted}}").unwrap();
+/// assert_eq!(template.render(&lambdas.overlay(&data)), "<a>, <b>, <c>");
+/// ```
+pub struct LambdaHelper<'h, 'r, 'a> {
+    renderer: &'h mut Renderer<'r>,
+    stack: &'h mut Stack<'a>,
+    section: Option<&'h str>,
+    children: &'h Segments,
+    indent: &'h str,
+    out: &'h mut dyn Write,
+}
+
+impl<'h, 'r, 'a> LambdaHelper<'h, 'r, 'a> {
+    pub(crate) fn new(
+        renderer: &'h mut Renderer<'r>, stack: &'h mut Stack<'a>,
+        section: Option<&'h str>, children: &'h Segments,
+        indent: &'h str, out: &'h mut dyn Write
+    ) -> Self {
+        LambdaHelper { renderer, stack, section, children, indent, out }
+    }
+
+    /// Unprocessed content of the section, None in interpolation position.
+    pub fn section(&self) -> Option<&str> {
+        self.section
+    }
+
+    /// Context of a name, resolved from the context where the lambda is used.
+    pub fn context(&mut self, name: &str) -> Option<ContextRef<'a>> {
+        self.stack.context(name)
+    }
+
+    /// Render the content of the section in the current context.
+    pub fn render(&mut self) -> Result<(), RenderError> {
+        self.renderer.render_segments(self.children, self.stack, self.indent, self.out)
+    }
+
+    /// Render the content of the section with `context` on top of the
+    /// current context.
+    pub fn render_with(&mut self, context: ContextRef<'a>) -> Result<(), RenderError> {
+        let len = self.stack.len();
+        self.stack.push_context(context);
+        let result = self.render();
+        self.stack.truncate(len);
+        result
+    }
+
+    /// Write text as is.
+    pub fn write(&mut self, text: &str) -> Result<(), RenderError> {
+        Ok(self.out.write_str(text)?)
+    }
+
+    /// Write text escaped as by `{{name}}`.
+    pub fn write_escaped(&mut self, text: &str) -> Result<(), RenderError> {
+        Ok(self.renderer.escaper().escape(text, self.out)?)
+    }
+}
@@ -0,0 +1,123 @@
+use std::cell::OnceCell;
+use std::fmt;
+
+use crate::{Context, ContextValue, ContextRef, ContextRefIterator, Lambda};
+
+
+/// [Context] produced by a function when first used in a render.
+///
+/// The function is called the first time the renderer resolves the context,
+/// and its result is used in its place until the end of the render. This
+/// allows data that is costly to produce (such as the rows of a database
+/// query) to be provided only if the template uses it, and to be fresh in
+/// each render.
+///
+/// Outside of the renderer, as when the [LazyContext] is the root of a
+/// [LambdasOverlay](crate::LambdasOverlay) or is accessed with
+/// [LazyContext::get], the result is kept in the [LazyContext] until
+/// [LazyContext::reset] is called.
+///
+/// # Sample
+///
+/// ```
+/// use mustache::{Template, LazyContext, Context, ContextRef};
+/// use std::{cell::Cell, rc::Rc};
+///
+/// struct Page {
+///     rows: LazyContext<Vec<String>>,
+/// }
+///
+/// impl Context for Page {
+///     fn child(&self, name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+///         match name {
+///             "rows" => Some(&self.rows),
+///             _ => None
+///         }
+///     }
+///     // ...
+/// #   fn children(&self) -> Option<mustache::ContextRefIterator<'_>> { None }
+/// #   fn value(&self) -> mustache::ContextValue { mustache::ContextValue::Text("".to_owned()) }
+/// #   fn is_falsy(&self) -> bool { false }
+/// }
+///
+/// let queries = Rc::new(Cell::new(0));
+/// let counter = Rc::clone(&queries);
+/// let page = Page {
+///     rows: LazyContext::new(move || {
+///         counter.set(counter.get() + 1);
+///         vec!["a".to_owned(), "b".to_owned()]
+///     })
+/// };
+///
+/// let template = Template::from("{{#rows}}{{.}}{{/rows}} {{#rows}}{{.}}{{/rows}}").unwrap();
+/// assert_eq!(template.render(&page), "ab ab");
+/// assert_eq!(queries.get(), 1);
+///
+/// assert_eq!(Template::from("none").unwrap().render(&page), "none");
+/// assert_eq!(queries.get(), 1);
+///
+/// assert_eq!(template.render(&page), "ab ab");
+/// assert_eq!(queries.get(), 2);
+/// ```
+pub struct LazyContext<C> {
+    fun: Box<dyn Fn() -> C>,
+    value: OnceCell<C>,
+}
+
+impl<C: Context> LazyContext<C> {
+    pub fn new<F>(fun: F) -> Self
+    where F: Fn() -> C + 'static {
+        LazyContext { fun: Box::new(fun), value: OnceCell::new() }
+    }
+
+    /// The context, calling the function if not done yet.
+    pub fn get(&self) -> &C {
+        self.value.get_or_init(|| (self.fun)())
+    }
+
+    /// True if the function has been called.
+    pub fn is_evaluated(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    /// Drop the result, the function being called again on next access.
+    pub fn reset(&mut self) {
+        self.value.take();
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for LazyContext<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LazyContext").field(&self.value.get()).finish()
+    }
+}
+
+impl<C: Context + 'static> Context for LazyContext<C> {
+    fn child(&self, name: &str, section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        self.get().child(name, section)
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        self.get().children()
+    }
+
+    fn value(&self) -> ContextValue {
+        self.get().value()
+    }
+
+    fn is_falsy(&self) -> bool {
+        self.get().is_falsy()
+    }
+
+    fn lambda(&self) -> Option<&Lambda> {
+        self.get().lambda()
+    }
+
+    fn computed(&self, name: &str) -> Option<Box<dyn Context + '_>> {
+        self.get().computed(name)
+    }
+
+    fn lazy(&self) -> Option<Box<dyn Context>> {
+        Some(Box::new((self.fun)()))
+    }
+}
@@ -4,11 +4,12 @@
 //! in a [Context], getting partials from a [TemplateStore].
 //! 
 //! This implementation support all core module as well as the optional
-//! inheritance and dynamic-partials modules, passing relevant tests
+//! inheritance, dynamic-partials and lambdas modules, passing relevant tests
 //! in [`specs`].
 //! 
-//! The [Context] trait can support implementations that provide lambdas.
-//! In such implementation the value method of [Context] should return
+//! Lambdas can be attached to a render with [Lambdas], overlaying any
+//! [Context]. The [Context] trait can also support implementations that
+//! provide lambdas themselves: the value method of [Context] should return
 //! a [ContextValue::Template] carrying the template produced by the lambda.
 //! An example of such an implementation is provided in [MapsAndLists].
 //! 
@@ -64,14 +65,43 @@
 //! [`specs`]: https://github.com/mustache/spec
 //! [`Context`]: crate::Context
 mod template;
+mod ast;
+mod references;
+mod schema;
+mod dependencies;
+mod lint;
+mod file_system;
+mod error;
+mod escape;
 mod reader;
 mod context;
 mod json;
 mod yaml;
 mod maps_and_lists;
+mod lambdas;
+mod lazy;
+mod filters;
+mod ser;
+mod primitives;
 
-pub use self::template::{Template, TemplateStore, TemplateMap};
+pub use self::template::{Template, CompileOptions, RenderOptions, TemplateStore, TemplateMap};
+pub use self::ast::{Node, Nodes, Span, Visitor};
+pub use self::references::{Reference, ReferenceKind};
+pub use self::dependencies::Dependencies;
+pub use self::lint::{Lint, LintKind};
+pub use self::file_system::{FileSystemStore, HotReloadStore};
+pub use self::error::{TemplateError, ErrorKind, RenderError, RenderErrorKind, StoreError};
+pub use self::escape::{
+    Escaper, HtmlEscaper, XmlEscaper, JsonEscaper, LatexEscaper, ShellEscaper, NoEscaper
+};
 pub use self::context::{Context, ContextValue, ContextRef, ContextRefIterator};
 pub use self::json::JsonValue;
 pub use self::yaml::YamlValue;
-pub use self::maps_and_lists::MapsAndLists;
+pub use self::maps_and_lists::{MapsAndLists, SyncMapsAndLists};
+pub use self::lambdas::{Lambda, Lambdas, LambdasOverlay, LambdaHelper};
+pub use self::lazy::LazyContext;
+pub use self::filters::{Filter, Filters};
+pub use self::ser::{SerContext, SerError};
+
+#[cfg(feature = "derive")]
+pub use mustache_derive::Context;
@@ -0,0 +1,177 @@
+use std::fmt;
+
+use crate::Span;
+use crate::error::line_and_column;
+use crate::reader::{Reader, Token};
+
+
+/// Suspicious construct found by [Template::lint](crate::Template::lint).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lint {
+    /// What looks wrong.
+    pub kind: LintKind,
+    /// Location of the tag.
+    pub span: Span,
+    /// Source text of the tag, including delimiters.
+    pub tag: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {} `{}`", self.span.line, self.span.column, self.kind, self.tag)
+    }
+}
+
+/// Category of a [Lint].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LintKind {
+    /// A set delimiters tag is not followed by any tag using the new
+    /// delimiters, or sets the current delimiters.
+    UnusedDelimiters,
+    /// A section is closed with a different qualifier than it was opened
+    /// with, such as `{{#?x}}...{{/x}}`.
+    WrongCloseQualifier,
+    /// A block is not an override of a parent tag in a template using a
+    /// parent, or is nested in a section of a parent tag and ignored.
+    BlockOutsideParent,
+    /// A parent tag overrides the same block twice, only the last one
+    /// being used.
+    DuplicateBlock,
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            LintKind::UnusedDelimiters => "unused delimiters change",
+            LintKind::WrongCloseQualifier => "wrong close qualifier",
+            LintKind::BlockOutsideParent => "block outside a parent",
+            LintKind::DuplicateBlock => "duplicate block",
+        };
+        f.write_str(text)
+    }
+}
+
+
+// tag opened and not yet closed
+enum Open<'a> {
+    Section(&'a str, &'a str),
+    Block(&'a str),
+    // with the names of the blocks it overrides
+    Parent(&'a str, &'a str, Vec<&'a str>),
+}
+
+impl<'a> Open<'a> {
+    fn tag(&self) -> (&'a str, &'a str) {
+        match *self {
+            Open::Section(name, qualifier) | Open::Parent(name, qualifier, _) => (name, qualifier),
+            Open::Block(name) => (name, ""),
+        }
+    }
+}
+
+struct Linter<'a> {
+    input: &'a str,
+    lints: Vec<Lint>,
+    open: Vec<Open<'a>>,
+    // set delimiters tag with no tag using them yet
+    unused: Option<Lint>,
+    // blocks outside any parent or block tag, reported if there is a parent tag
+    top_blocks: Vec<Lint>,
+    has_parent: bool,
+}
+
+impl<'a> Linter<'a> {
+    fn lint(&self, kind: LintKind, (start, end): (usize, usize)) -> Lint {
+        let (line, column) = line_and_column(self.input, start);
+        Lint { kind, span: Span { start, end, line, column }, tag: self.input[start..end].to_owned() }
+    }
+
+    fn open_block(&mut self, name: &'a str, span: (usize, usize)) {
+        let lint = self.lint(LintKind::BlockOutsideParent, span);
+        let container = self.open.iter_mut().rev()
+            .enumerate()
+            .find(|(_, open)| !matches!(open, Open::Section(..)));
+        match container {
+            Some((0, Open::Parent(_, _, blocks))) => {
+                if blocks.contains(&name) {
+                    self.lints.push(Lint { kind: LintKind::DuplicateBlock, ..lint });
+                }
+                blocks.push(name);
+            },
+            Some((_, Open::Parent(..))) => self.lints.push(lint),
+            Some(_) => {},
+            None => self.top_blocks.push(lint)
+        }
+        self.open.push(Open::Block(name));
+    }
+
+    fn close(&mut self, name: &str, qualifier: &str, span: (usize, usize)) {
+        match self.open.last().map(Open::tag) {
+            Some(open) if open == (name, qualifier) => {},
+            Some((open, _)) if open == name => {
+                let lint = self.lint(LintKind::WrongCloseQualifier, span);
+                self.lints.push(lint);
+            },
+            // close the enclosing tag it matches if any, as when compiling
+            _ => match self.open.iter().rposition(|open| open.tag() == (name, qualifier)) {
+                Some(position) => self.open.truncate(position + 1),
+                None => return
+            }
+        }
+        self.open.pop();
+    }
+}
+
+
+// lints of a template source
+pub(crate) fn lint(input: &str) -> Vec<Lint> {
+    let mut reader = Reader::new(input);
+    let mut linter = Linter {
+        input,
+        lints: Vec::new(),
+        open: Vec::new(),
+        unused: None,
+        top_blocks: Vec::new(),
+        has_parent: false,
+    };
+    while let Some(token) = reader.pop_front() {
+        let span = reader.span();
+        // any tag uses the current delimiters, except one changing them
+        if !matches!(token, Token::Text(..) | Token::Error(_)) {
+            match linter.unused.take() {
+                Some(lint) if matches!(token, Token::Delimiters(..)) => linter.lints.push(lint),
+                _ => {}
+            }
+        }
+        match token {
+            Token::Section(name, _, is_seqcheck) =>
+                linter.open.push(Open::Section(name, if is_seqcheck { "?" } else { "" })),
+            Token::InvertedSection(name) =>
+                linter.open.push(Open::Section(name, "")),
+            Token::Block(name) =>
+                linter.open_block(name, span),
+            Token::Parent(name, is_dynamic, _) => {
+                linter.has_parent = true;
+                linter.open.push(Open::Parent(name, if is_dynamic { "*" } else { "" }, Vec::new()));
+            },
+            Token::EndSection(name, qualifier, _) =>
+                linter.close(name, qualifier, span),
+            Token::Delimiters(od, cd) => {
+                let lint = linter.lint(LintKind::UnusedDelimiters, span);
+                if reader.delimiters() == (od.to_owned(), cd.to_owned()) {
+                    linter.lints.push(lint);
+                } else {
+                    linter.unused = Some(lint);
+                }
+                reader.set_delimiters(od, cd);
+            },
+            _ => {}
+        }
+    }
+    linter.lints.extend(linter.unused);
+    if linter.has_parent {
+        linter.lints.append(&mut linter.top_blocks);
+    }
+    linter.lints.sort_by_key(|lint| lint.span.start);
+    linter.lints
+}
@@ -1,5 +1,6 @@
 use std::{collections::HashMap, cell::RefCell, rc::Rc};
-use crate::{Context, ContextValue, ContextRef, ContextRefIterator};
+use std::sync::{Arc, OnceLock};
+use crate::{Context, ContextValue, ContextRef, ContextRefIterator, Lambda, LambdaHelper, LazyContext, RenderError};
 
 
 /// Minimun [Context] implementation.
@@ -63,6 +64,8 @@ enum Value {
     Sequence(Vec<MapsAndLists>),
     Lambda0(Box<dyn Fn() -> String>, RefCell<String>),
     Lambda1(Box<dyn Fn(&str) -> String>, Rc<str>, RefCell<String>),
+    Helper(Lambda),
+    Lazy(Box<LazyContext<MapsAndLists>>),
 }
 
 impl MapsAndLists {
@@ -115,6 +118,24 @@ impl MapsAndLists {
         ))
     }
 
+    /// Create a mustache lambda using a [LambdaHelper].
+    /// 
+    /// `fun` can render the content of the section against the current
+    /// context, look up names and write text that is not parsed as a template.
+    pub fn helper<T>(fun: T) -> MapsAndLists
+    where T: Fn(&mut LambdaHelper) -> Result<(), RenderError> + 'static {
+        MapsAndLists(Value::Helper(Lambda::helper(fun)))
+    }
+
+    /// Create a context produced by `fun` when first used in a render.
+    /// 
+    /// `fun` is called at most once per render, see [LazyContext]. It should
+    /// not produce a lambda, lambdas being called on use already.
+    pub fn lazy<T>(fun: T) -> MapsAndLists
+    where T: Fn() -> MapsAndLists + 'static {
+        MapsAndLists(Value::Lazy(Box::new(LazyContext::new(fun))))
+    }
+
     fn process_lambda(&self, section: &Option<(usize, usize)>) {
        match self {
             MapsAndLists(Value::Lambda0(lambda, result)) => {
@@ -143,6 +164,7 @@ impl Context for MapsAndLists{
                         it as ContextRef
                     }
                 ),
+            MapsAndLists(Value::Lazy(lazy)) => lazy.child(name, section),
             _ => None
         }
     }
@@ -151,6 +173,7 @@ impl Context for MapsAndLists{
         match self {
             MapsAndLists(Value::Sequence(seq)) =>
                 Some(Box::new(seq.iter().map(|it| it as ContextRef))),
+            MapsAndLists(Value::Lazy(lazy)) => lazy.children(),
             _ => None
         }
     }
@@ -165,6 +188,7 @@ impl Context for MapsAndLists{
             MapsAndLists(Value::Lambda1(_, _, result)) => ContextValue::Template(
                 result.borrow().clone()
             ),
+            MapsAndLists(Value::Lazy(lazy)) => lazy.value(),
             _ => ContextValue::Text("".to_owned())
         }
     }
@@ -174,7 +198,245 @@ impl Context for MapsAndLists{
             MapsAndLists(Value::Null) => true,
             MapsAndLists(Value::Bool(b)) => !b,
             MapsAndLists(Value::Text(t)) => t.is_empty(),
+            MapsAndLists(Value::Lazy(lazy)) => lazy.is_falsy(),
+            _ => false
+        }
+    }
+
+    fn lambda(&self) -> Option<&Lambda> {
+        match self {
+            MapsAndLists(Value::Helper(lambda)) => Some(lambda),
+            MapsAndLists(Value::Lazy(lazy)) => lazy.lambda(),
+            _ => None
+        }
+    }
+
+    fn computed(&self, name: &str) -> Option<Box<dyn Context + '_>> {
+        match self {
+            MapsAndLists(Value::Lazy(lazy)) => lazy.computed(name),
+            _ => None
+        }
+    }
+
+    fn lazy(&self) -> Option<Box<dyn Context>> {
+        match self {
+            MapsAndLists(Value::Lazy(lazy)) => lazy.lazy(),
+            _ => None
+        }
+    }
+}
+
+
+/// Thread-safe counterpart of [MapsAndLists].
+/// 
+/// Lambdas must be [Send] and [Sync], and are called when their value is
+/// rendered so that a single context can be rendered concurrently.
+/// There are no helpers as in [MapsAndLists::helper], a [Lambda] being
+/// neither [Send] nor [Sync].
+/// 
+/// # Sample
+/// 
+/// ```
+/// use mustache::{Template, SyncMapsAndLists};
+/// use std::{collections::HashMap, sync::Arc, thread};
+/// 
+/// let source = Arc::from("{{#bold}}hi {{name}}{{/bold}}");
+/// let template = Template::from(&source).unwrap();
+/// let context = SyncMapsAndLists::mapping(
+///   vec![
+///     (String::from("name"), SyncMapsAndLists::text("bob")),
+///     (String::from("bold"), SyncMapsAndLists::lambda1(
+///         |s| format!("<b>{}</b>", s),
+///         &source
+///     )),
+///   ].into_iter().collect::<HashMap<_, _>>()
+/// );
+/// 
+/// thread::scope(|scope| {
+///     for _ in 0..4 {
+///         scope.spawn(|| assert_eq!(template.render(&context), "<b>hi bob</b>"));
+///     }
+/// });
+/// ```
+pub struct SyncMapsAndLists(SyncValue);
+
+enum SyncValue {
+    Null,
+    Bool(bool),
+    Text(String),
+    Mapping(HashMap<String, SyncMapsAndLists>),
+    Sequence(Vec<SyncMapsAndLists>),
+    Lambda0(Box<dyn Fn() -> String + Send + Sync>),
+    Lambda1(Arc<SectionFn>, Arc<str>, Sections),
+}
+
+type SectionFn = dyn Fn(&str) -> String + Send + Sync;
+
+// a lambda processing a section bound to the section it is used for,
+// called when its value is rendered
+struct Section {
+    fun: Arc<SectionFn>,
+    template: Arc<str>,
+    section: Option<(usize, usize)>,
+}
+
+// sections a lambda was used for, appended to and never removed so that
+// references to them remain valid, bounded by the sections of the templates
+#[derive(Default)]
+struct Sections(OnceLock<BoundSection>);
+
+type BoundSection = (Option<(usize, usize)>, Box<Section>, Box<Sections>);
+
+impl SyncMapsAndLists {
+    pub fn null() -> SyncMapsAndLists {
+        SyncMapsAndLists(SyncValue::Null)
+    }
+
+    pub fn bool(b: bool) -> SyncMapsAndLists {
+        SyncMapsAndLists(SyncValue::Bool(b))
+    }
+
+    pub fn text(t: &str) -> SyncMapsAndLists {
+        SyncMapsAndLists(SyncValue::Text(t.to_owned()))
+    }
+
+    pub fn mapping(mapping: HashMap<String, SyncMapsAndLists>) -> SyncMapsAndLists {
+        SyncMapsAndLists(SyncValue::Mapping(mapping))
+    }
+
+    pub fn sequence(sequence: Vec<SyncMapsAndLists>) -> SyncMapsAndLists {
+        SyncMapsAndLists(SyncValue::Sequence(sequence))
+    }
+
+    /// Create a mustache lambda, see [MapsAndLists::lambda0].
+    pub fn lambda0<T>(fun: T) -> SyncMapsAndLists
+    where T: Fn() -> String + Send + Sync + 'static {
+        SyncMapsAndLists(SyncValue::Lambda0(Box::new(fun)))
+    }
+
+    /// Create a mustache lambda processing a section, see [MapsAndLists::lambda1].
+    pub fn lambda1<T>(fun: T, template: &Arc<str>) -> SyncMapsAndLists
+    where T: Fn(&str) -> String + Send + Sync + 'static {
+        SyncMapsAndLists(SyncValue::Lambda1(
+            Arc::new(fun),
+            Arc::clone(template),
+            Sections::default()
+        ))
+    }
+}
+
+impl Context for SyncMapsAndLists {
+    fn child(&self, name: &str, section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        match self {
+            SyncMapsAndLists(SyncValue::Mapping(obj)) => match obj.get(name) {
+                Some(SyncMapsAndLists(SyncValue::Lambda1(fun, template, sections))) => Some(
+                    sections.get(section, || Section {
+                        fun: Arc::clone(fun),
+                        template: Arc::clone(template),
+                        section,
+                    })
+                ),
+                Some(it) => Some(it as ContextRef),
+                None => None
+            },
+            _ => None
+        }
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        match self {
+            SyncMapsAndLists(SyncValue::Sequence(seq)) =>
+                Some(Box::new(seq.iter().map(|it| it as ContextRef))),
+            _ => None
+        }
+    }
+
+    fn value(&self) -> ContextValue {
+        match self {
+            SyncMapsAndLists(SyncValue::Bool(b)) => ContextValue::Text(b.to_string()),
+            SyncMapsAndLists(SyncValue::Text(text)) => ContextValue::Text(text.to_owned()),
+            SyncMapsAndLists(SyncValue::Lambda0(lambda)) => ContextValue::Template(lambda()),
+            _ => ContextValue::Text("".to_owned())
+        }
+    }
+
+    fn is_falsy(&self) -> bool {
+        match self {
+            SyncMapsAndLists(SyncValue::Null) => true,
+            SyncMapsAndLists(SyncValue::Bool(b)) => !b,
+            SyncMapsAndLists(SyncValue::Text(t)) => t.is_empty(),
             _ => false
         }
     }
 }
+
+impl Sections {
+    // the section bound for a position, made if used for the first time
+    fn get(&self, section: Option<(usize, usize)>, make: impl Fn() -> Section) -> &Section {
+        let mut sections = self;
+        loop {
+            let (bound, result, next) = sections.0.get_or_init(|| (section, Box::new(make()), Box::default()));
+            if *bound == section {
+                return result;
+            }
+            sections = next;
+        }
+    }
+}
+
+impl Context for Section {
+    fn child(&self, _name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        None
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        None
+    }
+
+    fn value(&self) -> ContextValue {
+        // with no section, use the entire template
+        let text = match self.section {
+            Some((start, end)) => &self.template[start..end],
+            None => &self.template,
+        };
+        ContextValue::Template((self.fun)(text))
+    }
+
+    fn is_falsy(&self) -> bool {
+        false
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Template;
+    use std::thread;
+
+    #[test]
+    fn sections_are_bound_once() {
+        let source = Arc::from("{{#wrap}}x{{/wrap}}{{^wrap}}y{{/wrap}}{{now}}{{#wrap}}x{{/wrap}}");
+        let template = Template::from(&source).unwrap();
+        let context = SyncMapsAndLists::mapping(HashMap::from([
+            (String::from("wrap"), SyncMapsAndLists::lambda1(|s| format!("[{}]", s), &source)),
+            (String::from("now"), SyncMapsAndLists::lambda0(|| String::from("!"))),
+        ]));
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| assert_eq!(template.render(&context), "[x]![x]"));
+            }
+        });
+        let SyncValue::Mapping(mapping) = &context.0 else { unreachable!() };
+        let SyncValue::Lambda1(_, _, sections) = &mapping["wrap"].0 else { unreachable!() };
+        let mut bound = Vec::new();
+        let mut sections = Some(sections);
+        while let Some((section, _, next)) = sections.and_then(|sections| sections.0.get()) {
+            bound.push(*section);
+            sections = Some(next);
+        }
+        bound.sort();
+        // inverted sections look the lambda up without a section
+        assert_eq!(bound, [None, Some((9, 10)), Some((54, 55))]);
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::{Context, ContextValue, ContextRef, ContextRefIterator, Lambda};
+
+
+// Context implementations for standard types, allowing application types
+// to expose their fields without copy (see the derive feature).
+//
+// Falsy values follow JsonValue: false, empty strings, zero and None.
+
+impl Context for String {
+    fn child(&self, _name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        None
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        None
+    }
+
+    fn value(&self) -> ContextValue {
+        ContextValue::Text(self.clone())
+    }
+
+    fn is_falsy(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl Context for str {
+    fn child(&self, _name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        None
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        None
+    }
+
+    fn value(&self) -> ContextValue {
+        ContextValue::Text(self.to_owned())
+    }
+
+    fn is_falsy(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl Context for bool {
+    fn child(&self, _name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        None
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        None
+    }
+
+    fn value(&self) -> ContextValue {
+        ContextValue::Text(self.to_string())
+    }
+
+    fn is_falsy(&self) -> bool {
+        !self
+    }
+}
+
+impl Context for char {
+    fn child(&self, _name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        None
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        None
+    }
+
+    fn value(&self) -> ContextValue {
+        ContextValue::Text(self.to_string())
+    }
+
+    fn is_falsy(&self) -> bool {
+        false
+    }
+}
+
+macro_rules! number_context {
+    ($($t:ty),*) => {
+        $(
+            impl Context for $t {
+                fn child(&self, _name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+                    None
+                }
+
+                fn children(&self) -> Option<ContextRefIterator<'_>> {
+                    None
+                }
+
+                fn value(&self) -> ContextValue {
+                    ContextValue::Text(self.to_string())
+                }
+
+                fn is_falsy(&self) -> bool {
+                    *self == <$t>::default()
+                }
+            }
+        )*
+    };
+}
+
+number_context!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+impl<T: Context> Context for Option<T> {
+    fn child(&self, name: &str, section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        self.as_ref()?.child(name, section)
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        self.as_ref()?.children()
+    }
+
+    fn value(&self) -> ContextValue {
+        match self {
+            Some(value) => value.value(),
+            None => ContextValue::Text("".to_owned())
+        }
+    }
+
+    fn is_falsy(&self) -> bool {
+        self.as_ref().is_none_or(|value| value.is_falsy())
+    }
+
+    fn lambda(&self) -> Option<&Lambda> {
+        self.as_ref()?.lambda()
+    }
+
+    fn computed(&self, name: &str) -> Option<Box<dyn Context + '_>> {
+        self.as_ref()?.computed(name)
+    }
+
+    fn lazy(&self) -> Option<Box<dyn Context>> {
+        self.as_ref()?.lazy()
+    }
+}
+
+impl<T: Context + ?Sized> Context for &T {
+    fn child(&self, name: &str, section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        (**self).child(name, section)
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        (**self).children()
+    }
+
+    fn value(&self) -> ContextValue {
+        (**self).value()
+    }
+
+    fn is_falsy(&self) -> bool {
+        (**self).is_falsy()
+    }
+
+    fn lambda(&self) -> Option<&Lambda> {
+        (**self).lambda()
+    }
+
+    fn computed(&self, name: &str) -> Option<Box<dyn Context + '_>> {
+        (**self).computed(name)
+    }
+
+    fn lazy(&self) -> Option<Box<dyn Context>> {
+        (**self).lazy()
+    }
+}
+
+impl<T: Context + ?Sized> Context for Box<T> {
+    fn child(&self, name: &str, section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        (**self).child(name, section)
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        (**self).children()
+    }
+
+    fn value(&self) -> ContextValue {
+        (**self).value()
+    }
+
+    fn is_falsy(&self) -> bool {
+        (**self).is_falsy()
+    }
+
+    fn lambda(&self) -> Option<&Lambda> {
+        (**self).lambda()
+    }
+
+    fn computed(&self, name: &str) -> Option<Box<dyn Context + '_>> {
+        (**self).computed(name)
+    }
+
+    fn lazy(&self) -> Option<Box<dyn Context>> {
+        (**self).lazy()
+    }
+}
+
+impl<T: Context> Context for Vec<T> {
+    fn child(&self, _name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        None
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        Some(Box::new(self.iter().map(|item| item as ContextRef)))
+    }
+
+    fn value(&self) -> ContextValue {
+        ContextValue::Text("".to_owned())
+    }
+
+    fn is_falsy(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Context, const N: usize> Context for [T; N] {
+    fn child(&self, _name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        None
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        Some(Box::new(self.iter().map(|item| item as ContextRef)))
+    }
+
+    fn value(&self) -> ContextValue {
+        ContextValue::Text("".to_owned())
+    }
+
+    fn is_falsy(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Context> Context for HashMap<String, T> {
+    fn child(&self, name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        self.get(name).map(|value| value as ContextRef)
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        None
+    }
+
+    fn value(&self) -> ContextValue {
+        ContextValue::Text("".to_owned())
+    }
+
+    fn is_falsy(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Context> Context for BTreeMap<String, T> {
+    fn child(&self, name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        self.get(name).map(|value| value as ContextRef)
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        None
+    }
+
+    fn value(&self) -> ContextValue {
+        ContextValue::Text("".to_owned())
+    }
+
+    fn is_falsy(&self) -> bool {
+        false
+    }
+}
@@ -1,11 +1,16 @@
 use std::cmp::{min, max};
 
+use crate::ErrorKind;
+use crate::filters::{self, Filter};
+
 pub(crate) struct Reader<'a> {
     input: &'a str,
     open_delimiter: &'a str,
     close_delimiter: &'a str,
     pos: usize,
-    after_standalone: usize
+    after_standalone: usize,
+    span: (usize, usize),
+    filters: bool,
 }
 
 impl<'a> Reader<'a> {
@@ -24,6 +29,8 @@ impl<'a> Reader<'a> {
             close_delimiter,
             pos,
             after_standalone,
+            span: (0, 0),
+            filters: false,
         }
     }
 
@@ -45,6 +52,7 @@ impl<'a> Reader<'a> {
         let starts_new_line = self.pos == 0 || &self.input[self.pos - 1.. self.pos] == "\n";
         let (text, after_text, after_standalone) = tail.span_text(self.open_delimiter, self.close_delimiter);
         self.after_standalone = self.pos + after_standalone;
+        self.span = (self.pos, self.pos + text.len());
         self.pos += after_text;
         Token::text(text, starts_new_line)
     }
@@ -71,6 +79,7 @@ impl<'a> Reader<'a> {
             } else {
                 ""
             };
+            self.span = (self.pos, self.pos + after_tag);
             self.pos += after_tag;
             if self.pos < self.after_standalone {
                 self.pos = match self.input[self.pos..self.after_standalone].find(self.open_delimiter) {
@@ -78,10 +87,18 @@ impl<'a> Reader<'a> {
                     _ => self.after_standalone
                 }
             }
-            Token::tag(text, indent, starts_new_line, before_tag, self.pos)
+            let token = Token::tag(text, indent, starts_new_line, before_tag, self.pos);
+            if self.filters {
+                token.filtered()
+            } else {
+                token
+            }
         } else {
-            self.pos = self.input.len();
-            Token::Error("missing close delimiter".to_owned())
+            let end_of_line = tail.find('\n').unwrap_or(tail.len());
+            self.span = (self.pos, self.pos + end_of_line);
+            // resume reading after the open delimiter
+            self.pos += self.open_delimiter.len();
+            Token::Error(ErrorKind::MissingCloseDelimiter)
         }
     }
 
@@ -93,9 +110,23 @@ impl<'a> Reader<'a> {
         }
     }
 
+    // read `{{name | filter:arg}}` chains in interpolation tags
+    pub(crate) fn set_filters(&mut self, enabled: bool) {
+        self.filters = enabled;
+    }
+
     pub(crate) fn delimiters(&self) -> (String, String) {
         (self.open_delimiter.to_owned(), self.close_delimiter.to_owned())
     }
+
+    pub(crate) fn input(&self) -> &'a str {
+        self.input
+    }
+
+    // (start, end) of the last token in input, including delimiters for tags
+    pub(crate) fn span(&self) -> (usize, usize) {
+        self.span
+    }
 }
 
 
@@ -104,6 +135,7 @@ impl<'a> Reader<'a> {
 pub(crate) enum Token<'a> {
     Text(&'a str, bool),
     Value(&'a str, bool, bool),
+    FilteredValue(&'a str, Vec<Filter>, bool, bool),
     Section(&'a str, usize, bool),
     InvertedSection(&'a str),
     Block(&'a str),
@@ -112,7 +144,7 @@ pub(crate) enum Token<'a> {
     Parent(&'a str, bool, &'a str),
     Comment(&'a str),
     Delimiters(&'a str, &'a str),
-    Error(String),
+    Error(ErrorKind),
 }
 
 impl<'a> Token<'a> {
@@ -158,6 +190,18 @@ impl<'a> Token<'a> {
             Token::Value("", true, starts_new_line)
         }
     }
+
+    fn filtered(self) -> Token<'a> {
+        match self {
+            Token::Value(text, is_escaped, starts_new_line) if text.contains('|') => {
+                match filters::parse(text) {
+                    Ok((name, filters)) => Token::FilteredValue(name, filters, is_escaped, starts_new_line),
+                    Err(kind) => Token::Error(kind)
+                }
+            },
+            token => token
+        }
+    }
 }
 
 fn qualified_tag<'a>(text: &'a str, qualifiers: &str) -> (&'a str, &'a str) {
@@ -174,13 +218,13 @@ fn qualified_tag<'a>(text: &'a str, qualifiers: &str) -> (&'a str, &'a str) {
 
 fn maybe_delimiters<'a>(text: &'a str) -> Result<(&'a str, &'a str), Token<'a>> {
     if text.contains('=') {
-        return Err(Token::Error("invalid delimiters tag".to_owned()));
+        return Err(Token::Error(ErrorKind::InvalidDelimiters));
     }
     let mut words = text.split_ascii_whitespace();
     if let (Some(od), Some(cd), None) = (words.next(), words.next(), words.next()) {
         Ok((od, cd))
     } else {
-        Err(Token::Error("invalid delimiters tag".to_owned()))
+        Err(Token::Error(ErrorKind::InvalidDelimiters))
     }
 }
 
@@ -413,7 +457,8 @@ mod tests {
         expect_sequence(
             "{{= +++   --- }}",
             vec![
-                Token::Error("missing close delimiter".to_owned())
+                Token::Error(ErrorKind::MissingCloseDelimiter),
+                Token::Text("= +++   --- }}", false)
             ]
         )
     }
@@ -423,7 +468,7 @@ mod tests {
         expect_sequence(
             "{{= |=   | =}}",
             vec![
-                Token::Error("invalid delimiters tag".to_owned())
+                Token::Error(ErrorKind::InvalidDelimiters)
             ]
         )
     }
@@ -433,7 +478,7 @@ mod tests {
         expect_sequence(
             "{{= |   =| =}}",
             vec![
-                Token::Error("invalid delimiters tag".to_owned())
+                Token::Error(ErrorKind::InvalidDelimiters)
             ]
         )
     }
@@ -494,6 +539,27 @@ mod tests {
         )
     }
 
+    #[test]
+    fn filtered_value() {
+        let mut reader = Reader::new("{{ a | upper }}{{{b|x:1}}}{{c|}}{{ d }}");
+        reader.set_filters(true);
+        let filter = |text| crate::filters::parse(text).unwrap().1;
+        assert_eq!(reader.pop_front(), Some(Token::FilteredValue("a", filter("a | upper"), true, true)));
+        assert_eq!(reader.pop_front(), Some(Token::FilteredValue("b", filter("b|x:1"), false, false)));
+        assert_eq!(reader.pop_front(), Some(Token::Error(ErrorKind::InvalidFilter)));
+        assert_eq!(reader.pop_front(), Some(Token::Value("d", true, false)));
+    }
+
+
+    #[test]
+    fn span_of_tag() {
+        let mut reader = Reader::new("ab\n  {{ x }}cd");
+        reader.pop_front();
+        assert_eq!(reader.span(), (0, 5));
+        assert_eq!(reader.pop_front(), Some(Token::Value("x", true, false)));
+        assert_eq!(reader.span(), (5, 12));
+    }
+
 
     fn expect_sequence(input: &str, tokens:Vec<Token<'_>>) {
         let mut reader = Reader::new(input);
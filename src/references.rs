@@ -0,0 +1,126 @@
+use std::fmt;
+
+use crate::{Node, Span, Visitor};
+
+
+/// Name used by a template, see [Template::references](crate::Template::references).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reference {
+    /// Position of the name.
+    pub kind: ReferenceKind,
+    /// Name as written in the tag.
+    pub name: String,
+    /// Name prefixed by the enclosing sections, each followed by `[]`,
+    /// such as `items[].price` for `{{#items}}{{price}}{{/items}}`.
+    ///
+    /// The path follows the nesting of sections, it does not account for
+    /// names resolved in an enclosing context at render time.
+    /// Partial names are not prefixed as they do not refer to data.
+    pub path: String,
+    /// Location of the tag.
+    pub span: Span,
+}
+
+/// Position of a [Reference].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReferenceKind {
+    /// `{{name}}`, `{{{name}}}` or `{{&name}}`.
+    Variable,
+    /// `{{#name}}`.
+    Section,
+    /// `{{#?name}}`.
+    SeqCheckSection,
+    /// `{{^name}}`.
+    InvertedSection,
+    /// `{{>name}}` or `{{<name}}`.
+    Partial,
+    /// `{{>*name}}` or `{{<*name}}`, the name referring to data.
+    DynamicPartial,
+}
+
+impl fmt::Display for ReferenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ReferenceKind::Variable => "variable",
+            ReferenceKind::Section => "section",
+            ReferenceKind::SeqCheckSection => "sequence check",
+            ReferenceKind::InvertedSection => "inverted section",
+            ReferenceKind::Partial => "partial",
+            ReferenceKind::DynamicPartial => "dynamic partial",
+        };
+        f.write_str(text)
+    }
+}
+
+
+// collect references, keeping the paths of enclosing sections
+#[derive(Default)]
+pub(crate) struct References {
+    pub(crate) references: Vec<Reference>,
+    // prefix of the sections being visited, None for those that do not iterate
+    sections: Vec<Option<String>>,
+}
+
+impl References {
+    fn prefix(&self) -> &str {
+        self.sections.iter().rev()
+            .find_map(|prefix| prefix.as_deref())
+            .unwrap_or("")
+    }
+
+    fn push(&mut self, kind: ReferenceKind, name: &str, span: Span) -> String {
+        let path = match kind {
+            ReferenceKind::Partial => name.to_owned(),
+            _ => join(self.prefix(), name)
+        };
+        self.references.push(Reference { kind, name: name.to_owned(), path: path.clone(), span });
+        path
+    }
+}
+
+impl<'t> Visitor<'t> for References {
+    fn enter(&mut self, node: &Node<'t>) -> bool {
+        match *node {
+            Node::Variable { name, span, .. } => {
+                self.push(ReferenceKind::Variable, name, span);
+            },
+            Node::Section { name, span, .. } => {
+                let path = self.push(ReferenceKind::Section, name, span);
+                self.sections.push(Some(path + "[]"));
+            },
+            // the content of these is rendered in the enclosing context
+            Node::SeqCheckSection { name, span, .. } => {
+                self.push(ReferenceKind::SeqCheckSection, name, span);
+                self.sections.push(None);
+            },
+            Node::InvertedSection { name, span, .. } => {
+                self.push(ReferenceKind::InvertedSection, name, span);
+                self.sections.push(None);
+            },
+            Node::Partial { name, dynamic, span } | Node::Parent { name, dynamic, span, .. } => {
+                let kind = if dynamic { ReferenceKind::DynamicPartial } else { ReferenceKind::Partial };
+                self.push(kind, name, span);
+                if let Node::Parent { .. } = node {
+                    self.sections.push(None);
+                }
+            },
+            Node::Block { .. } => self.sections.push(None),
+            Node::Text { .. } => {}
+        }
+        true
+    }
+
+    fn leave(&mut self, node: &Node<'t>) {
+        if node.children().is_some() {
+            self.sections.pop();
+        }
+    }
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    match (prefix, name) {
+        ("", name) => name.to_owned(),
+        (prefix, ".") => prefix.to_owned(),
+        (prefix, name) => format!("{}.{}", prefix, name)
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+use serde_json::{json, Map, Value};
+
+use crate::{Reference, ReferenceKind};
+
+
+// data shape inferred from references
+enum Shape {
+    // used as a condition only
+    Any,
+    Scalar,
+    Text,
+    Object(BTreeMap<String, Shape>),
+    Array(Box<Shape>),
+}
+
+impl Shape {
+    // combine with another use of the same name, keeping the most specific
+    fn merge(self, other: Shape) -> Shape {
+        match (self, other) {
+            (Shape::Any, shape) | (shape, Shape::Any) => shape,
+            (Shape::Object(mut fields), Shape::Object(others)) => {
+                for (name, shape) in others {
+                    insert(&mut fields, name, shape);
+                }
+                Shape::Object(fields)
+            },
+            (Shape::Array(items), Shape::Array(others)) => Shape::Array(Box::new(items.merge(*others))),
+            // a section on an object renders once with the object as context
+            (Shape::Object(fields), Shape::Array(items)) | (Shape::Array(items), Shape::Object(fields)) =>
+                match *items {
+                    Shape::Object(others) => Shape::Object(fields).merge(Shape::Object(others)),
+                    _ => Shape::Object(fields)
+                },
+            (shape @ (Shape::Object(_) | Shape::Array(_)), _) |
+            (_, shape @ (Shape::Object(_) | Shape::Array(_))) => shape,
+            (Shape::Text, _) | (_, Shape::Text) => Shape::Text,
+            (Shape::Scalar, Shape::Scalar) => Shape::Scalar,
+        }
+    }
+
+    fn schema(&self) -> Value {
+        match self {
+            Shape::Any => json!({}),
+            Shape::Scalar => json!({ "type": ["string", "number", "boolean"] }),
+            Shape::Text => json!({ "type": "string" }),
+            Shape::Object(fields) => {
+                let properties = fields.iter()
+                    .map(|(name, shape)| (name.clone(), shape.schema()))
+                    .collect::<Map<_, _>>();
+                json!({ "type": "object", "properties": properties })
+            },
+            Shape::Array(items) => json!({ "type": "array", "items": items.schema() }),
+        }
+    }
+}
+
+fn insert(fields: &mut BTreeMap<String, Shape>, name: String, shape: Shape) {
+    let merged = match fields.remove(&name) {
+        Some(current) => current.merge(shape),
+        None => shape
+    };
+    fields.insert(name, merged);
+}
+
+// shape for a path such as `items[].price` ending with a leaf shape
+fn shape_of(steps: &[&str], leaf: Shape) -> Option<(String, Shape)> {
+    let (step, tail) = steps.split_first()?;
+    let (name, is_array) = match step.strip_suffix("[]") {
+        Some(name) => (name, true),
+        None => (*step, false)
+    };
+    if name.is_empty() || name == "." {
+        return None;
+    }
+    let inner = if tail.is_empty() {
+        leaf
+    } else {
+        match shape_of(tail, leaf) {
+            Some((field, shape)) => Shape::Object(BTreeMap::from([(field, shape)])),
+            None => Shape::Any
+        }
+    };
+    let shape = if is_array { Shape::Array(Box::new(inner)) } else { inner };
+    Some((name.to_owned(), shape))
+}
+
+
+// JSON Schema for data referenced by a template
+pub(crate) fn schema(references: &[Reference]) -> Value {
+    let mut fields = BTreeMap::new();
+    for reference in references {
+        let leaf = match reference.kind {
+            ReferenceKind::Variable => Shape::Scalar,
+            ReferenceKind::Section | ReferenceKind::InvertedSection => Shape::Any,
+            ReferenceKind::SeqCheckSection => Shape::Array(Box::new(Shape::Any)),
+            ReferenceKind::DynamicPartial => Shape::Text,
+            ReferenceKind::Partial => continue,
+        };
+        let steps = reference.path.split('.').collect::<Vec<_>>();
+        if let Some((name, shape)) = shape_of(&steps, leaf) {
+            insert(&mut fields, name, shape);
+        }
+    }
+    let mut schema = Shape::Object(fields).schema();
+    schema["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+    schema
+}
@@ -0,0 +1,691 @@
+use std::{cell::OnceCell, fmt, rc::Rc};
+use serde::ser::{self, Serialize};
+use crate::{Context, ContextValue, ContextRef, ContextRefIterator};
+
+
+/// [Context] adapter for any type implementing [serde::Serialize].
+///
+/// The data is not copied: each name looked up while rendering walks the
+/// data with serde along its path, skipping the other fields without
+/// serializing them, and only the shape of the value found is kept. Values
+/// map to Mustache as follows:
+/// - structs and maps are mappings (map keys must serialize as strings,
+///   numbers, chars or booleans, other entries are ignored)
+/// - sequences, tuples, `Vec` and slices are sequences
+/// - `None`, `()` and unit structs are null
+/// - numbers, chars and strings are text
+/// - unit enum variants are the name of the variant, other variants are
+///   a mapping of the variant name to its content (as in JSON)
+///
+/// Falsy values are the same as for [JsonValue](crate::JsonValue): null,
+/// false, empty strings and zero.
+///
+/// As lookups start from the root, names in the items of a long sequence
+/// walk the sequence each time: converting such data with
+/// [serde_json::to_value] once may be faster.
+///
+/// A [Serialize] implementation returning an error renders as null, the
+/// first error being available from [SerContext::error].
+///
+/// # Sample
+///
+/// ```
+/// use mustache::{Template, SerContext};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Team {
+///     name: String,
+///     members: Vec<Member>,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Member {
+///     name: &'static str,
+///     lead: bool,
+///     role: Option<&'static str>,
+/// }
+///
+/// let team = Team {
+///     name: "core".to_owned(),
+///     members: vec![
+///         Member { name: "ann", lead: true, role: Some("dev") },
+///         Member { name: "bob", lead: false, role: None },
+///     ],
+/// };
+/// let template = Template::from(
+///     "{{name}}:{{#members}} {{name}}{{#lead}}*{{/lead}}{{#role}}({{.}}){{/role}}{{/members}}"
+/// ).unwrap();
+///
+/// let result = template.render(&SerContext::new(&team));
+/// assert_eq!(result, "core: ann*(dev) bob");
+/// ```
+pub struct SerContext<'a> {
+    root: Node<'a>,
+}
+
+impl<'a> SerContext<'a> {
+    /// Create a [SerContext] over `data`, which is walked on lookups.
+    pub fn new<T: Serialize + ?Sized>(data: &'a T) -> Self {
+        let shared = Rc::new(Shared {
+            walk: Box::new(move |path, items| data.serialize(Probe { path, items })),
+            error: OnceCell::new(),
+        });
+        let shape = shared.walk(&[], false);
+        SerContext { root: Node::new(shared, Vec::new(), shape) }
+    }
+
+    /// First error returned by the [Serialize] implementation, if any.
+    pub fn error(&self) -> Option<&SerError> {
+        self.root.shared.error.get()
+    }
+}
+
+impl Context for SerContext<'_> {
+    fn child(&self, name: &str, section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        self.root.child(name, section)
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        self.root.children()
+    }
+
+    fn value(&self) -> ContextValue {
+        self.root.value()
+    }
+
+    fn is_falsy(&self) -> bool {
+        self.root.is_falsy()
+    }
+}
+
+
+// step in the path from the root to a value
+#[derive(Clone)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+// what is known of a value without walking into it, sequences having
+// the shapes of their items when requested
+enum Shape {
+    Null,
+    Bool(bool),
+    Number(String, bool),
+    Text(String),
+    Mapping,
+    Sequence(Vec<Shape>),
+}
+
+impl Shape {
+    fn number<N: PartialEq + Default + ToString>(n: N) -> Shape {
+        Shape::Number(n.to_string(), n == N::default())
+    }
+}
+
+type Walk<'a> = dyn Fn(&[Step], bool) -> Result<Option<Shape>, SerError> + 'a;
+
+// the data, shared by all nodes
+struct Shared<'a> {
+    walk: Box<Walk<'a>>,
+    error: OnceCell<SerError>,
+}
+
+impl Shared<'_> {
+    // shape of the value at path, None if there is no such value
+    fn walk(&self, path: &[Step], items: bool) -> Option<Shape> {
+        (self.walk)(path, items).unwrap_or_else(|err| {
+            let _ = self.error.set(err);
+            Some(Shape::Null)
+        })
+    }
+}
+
+// value at a path, with the names and items looked up in it so far
+struct Node<'a> {
+    shared: Rc<Shared<'a>>,
+    path: Vec<Step>,
+    shape: Shape,
+    fields: Fields<'a>,
+    items: OnceCell<Vec<Node<'a>>>,
+}
+
+// names looked up in a mapping, appended as they are used
+#[derive(Default)]
+struct Fields<'a>(OnceCell<(String, Option<Box<Node<'a>>>, Box<Fields<'a>>)>);
+
+impl<'a> Node<'a> {
+    fn new(shared: Rc<Shared<'a>>, path: Vec<Step>, shape: Option<Shape>) -> Self {
+        Node {
+            shared,
+            path,
+            shape: shape.unwrap_or(Shape::Null),
+            fields: Fields::default(),
+            items: OnceCell::new(),
+        }
+    }
+
+    fn step(&self, step: Step) -> Vec<Step> {
+        let mut path = self.path.clone();
+        path.push(step);
+        path
+    }
+
+    fn field(&self, name: &str) -> Option<&Node<'a>> {
+        let mut fields = &self.fields;
+        while let Some((key, node, next)) = fields.0.get() {
+            if key == name {
+                return node.as_deref();
+            }
+            fields = next;
+        }
+        let path = self.step(Step::Key(name.to_owned()));
+        let node = self.shared.walk(&path, false)
+            .map(|shape| Box::new(Node::new(Rc::clone(&self.shared), path, Some(shape))));
+        fields.0.get_or_init(|| (name.to_owned(), node, Box::default())).1.as_deref()
+    }
+
+    fn items(&self) -> &[Node<'a>] {
+        self.items.get_or_init(|| match self.shared.walk(&self.path, true) {
+            Some(Shape::Sequence(shapes)) => shapes.into_iter().enumerate()
+                .map(|(idx, shape)| Node::new(Rc::clone(&self.shared), self.step(Step::Index(idx)), Some(shape)))
+                .collect(),
+            _ => Vec::new()
+        })
+    }
+}
+
+impl Context for Node<'_> {
+    fn child(&self, name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        match self.shape {
+            Shape::Mapping => self.field(name).map(|node| node as ContextRef),
+            _ => None
+        }
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        match self.shape {
+            Shape::Sequence(_) => Some(Box::new(self.items().iter().map(|node| node as ContextRef))),
+            _ => None
+        }
+    }
+
+    fn value(&self) -> ContextValue {
+        let text = match &self.shape {
+            Shape::Bool(b) => b.to_string(),
+            Shape::Number(n, _) => n.clone(),
+            Shape::Text(s) => s.clone(),
+            _ => "".to_owned()
+        };
+        ContextValue::Text(text)
+    }
+
+    fn is_falsy(&self) -> bool {
+        match &self.shape {
+            Shape::Null => true,
+            Shape::Bool(b) => !b,
+            Shape::Number(_, is_zero) => *is_zero,
+            Shape::Text(s) => s.is_empty(),
+            _ => false
+        }
+    }
+}
+
+
+/// Error returned by a [Serialize] implementation, see [SerContext::error].
+#[derive(Debug)]
+pub struct SerError(String);
+
+impl fmt::Display for SerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SerError {}
+
+impl ser::Error for SerError {
+    fn custom<M: fmt::Display>(msg: M) -> Self {
+        SerError(msg.to_string())
+    }
+}
+
+
+// serializer following a path, producing the shape of the value at its end
+// (None if not found) without serializing anything else
+struct Probe<'p> {
+    path: &'p [Step],
+    // collect the shapes of the items of a sequence at the end of the path
+    items: bool,
+}
+
+impl<'p> Probe<'p> {
+    fn scalar(self, shape: Shape) -> Result<Option<Shape>, SerError> {
+        Ok(self.path.is_empty().then_some(shape))
+    }
+
+    // compound value, entered for an enum variant if the path goes through it
+    fn compound(self, variant: Option<&'static str>, mapping: bool) -> Compound<'p> {
+        let (path, found) = match (variant, self.path) {
+            (None, path) => (Some(path), None),
+            (Some(_), []) => (None, Some(Shape::Mapping)),
+            (Some(variant), [Step::Key(key), path @ ..]) if key == variant => (Some(path), None),
+            (Some(_), _) => (None, None),
+        };
+        Compound { path, items: self.items, mapping, index: 0, shapes: Vec::new(), key: false, found }
+    }
+}
+
+impl<'p> ser::Serializer for Probe<'p> {
+    type Ok = Option<Shape>;
+    type Error = SerError;
+    type SerializeSeq = Compound<'p>;
+    type SerializeTuple = Compound<'p>;
+    type SerializeTupleStruct = Compound<'p>;
+    type SerializeTupleVariant = Compound<'p>;
+    type SerializeMap = Compound<'p>;
+    type SerializeStruct = Compound<'p>;
+    type SerializeStructVariant = Compound<'p>;
+
+    fn serialize_bool(self, v: bool) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::number(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::number(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::number(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::number(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::number(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::number(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::number(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::number(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::number(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::number(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::number(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::number(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::Text(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::Text(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Option<Shape>, SerError> {
+        match self.path {
+            [] if self.items => Ok(Some(Shape::Sequence(v.iter().map(|b| Shape::number(*b)).collect()))),
+            [] => Ok(Some(Shape::Sequence(Vec::new()))),
+            [Step::Index(idx)] => Ok(v.get(*idx).map(|b| Shape::number(*b))),
+            _ => Ok(None)
+        }
+    }
+
+    fn serialize_none(self) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Option<Shape>, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::Null)
+    }
+
+    fn serialize_unit_variant(
+        self, _name: &'static str, _index: u32, variant: &'static str
+    ) -> Result<Option<Shape>, SerError> {
+        self.scalar(Shape::Text(variant.to_owned()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self, _name: &'static str, value: &T
+    ) -> Result<Option<Shape>, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self, _name: &'static str, _index: u32, variant: &'static str, value: &T
+    ) -> Result<Option<Shape>, SerError> {
+        match self.path {
+            [] => Ok(Some(Shape::Mapping)),
+            [Step::Key(key), path @ ..] if key == variant => value.serialize(Probe { path, items: self.items }),
+            _ => Ok(None)
+        }
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'p>, SerError> {
+        Ok(self.compound(None, false))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'p>, SerError> {
+        Ok(self.compound(None, false))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'p>, SerError> {
+        Ok(self.compound(None, false))
+    }
+
+    fn serialize_tuple_variant(
+        self, _name: &'static str, _index: u32, variant: &'static str, _len: usize
+    ) -> Result<Compound<'p>, SerError> {
+        Ok(self.compound(Some(variant), false))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'p>, SerError> {
+        Ok(self.compound(None, true))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'p>, SerError> {
+        Ok(self.compound(None, true))
+    }
+
+    fn serialize_struct_variant(
+        self, _name: &'static str, _index: u32, variant: &'static str, _len: usize
+    ) -> Result<Compound<'p>, SerError> {
+        Ok(self.compound(Some(variant), true))
+    }
+}
+
+
+// sequence or mapping on the path, or around it for other enum variants
+struct Compound<'p> {
+    // rest of the path in the compound, None if the value is not in it
+    path: Option<&'p [Step]>,
+    items: bool,
+    mapping: bool,
+    index: usize,
+    shapes: Vec<Shape>,
+    // the current map key is the one looked up
+    key: bool,
+    found: Option<Shape>,
+}
+
+impl Compound<'_> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        match self.path {
+            Some([]) if self.items => {
+                let shape = value.serialize(Probe { path: &[], items: false })?;
+                self.shapes.push(shape.unwrap_or(Shape::Null));
+            },
+            Some([Step::Index(idx), path @ ..]) if *idx == self.index => {
+                self.found = value.serialize(Probe { path, items: self.items })?;
+            },
+            _ => {}
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), SerError> {
+        if let Some([Step::Key(name), path @ ..]) = self.path {
+            if name == key {
+                self.found = value.serialize(Probe { path, items: self.items })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn done(self) -> Result<Option<Shape>, SerError> {
+        Ok(match self.path {
+            Some([]) if self.mapping => Some(Shape::Mapping),
+            Some([]) => Some(Shape::Sequence(self.shapes)),
+            _ => self.found
+        })
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = Option<Shape>;
+    type Error = SerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Option<Shape>, SerError> {
+        self.done()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = Option<Shape>;
+    type Error = SerError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Option<Shape>, SerError> {
+        self.done()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = Option<Shape>;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Option<Shape>, SerError> {
+        self.done()
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = Option<Shape>;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Option<Shape>, SerError> {
+        self.done()
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = Option<Shape>;
+    type Error = SerError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerError> {
+        // keys that are not scalar are dropped with their value
+        self.key = match self.path {
+            Some([Step::Key(name), ..]) => match key.serialize(Probe { path: &[], items: false })? {
+                Some(Shape::Text(key) | Shape::Number(key, _)) => &key == name,
+                Some(Shape::Bool(b)) => b.to_string() == *name,
+                _ => false
+            },
+            _ => false
+        };
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        if std::mem::take(&mut self.key) {
+            if let Some([_, path @ ..]) = self.path {
+                self.found = value.serialize(Probe { path, items: self.items })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Option<Shape>, SerError> {
+        self.done()
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = Option<Shape>;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Option<Shape>, SerError> {
+        self.done()
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = Option<Shape>;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Option<Shape>, SerError> {
+        self.done()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Template;
+    use std::collections::BTreeMap;
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[test]
+    fn enums_are_rendered_as_in_json() {
+        let shapes = vec![Shape::Empty, Shape::Circle(1.5), Shape::Rect { w: 2, h: 0 }];
+        let template = Template::from(
+            "{{#.}}[{{.}}{{Circle}}{{#Rect}}{{w}}x{{h}}{{^h}}!{{/h}}{{/Rect}}]{{/.}}"
+        ).unwrap();
+        assert_eq!(template.render(&SerContext::new(&shapes)), "[Empty][1.5][2x0!]");
+    }
+
+    #[test]
+    fn maps_with_scalar_keys() {
+        let map = BTreeMap::from([(1, "one"), (2, "")]);
+        let template = Template::from("{{1}}{{^2}}-{{/2}}").unwrap();
+        assert_eq!(template.render(&SerContext::new(&map)), "one-");
+    }
+
+    #[derive(Serialize)]
+    enum Pair {
+        Both(u8, &'static str),
+    }
+
+    #[derive(Serialize)]
+    struct Data {
+        pairs: Vec<Pair>,
+        name: Option<&'static str>,
+        bytes: &'static [u8],
+        map: BTreeMap<bool, BTreeMap<char, u8>>,
+    }
+
+    #[test]
+    fn nested_values_are_found_by_path() {
+        let data = Data {
+            pairs: vec![Pair::Both(1, "a"), Pair::Both(0, "")],
+            name: Some("x"),
+            bytes: b"ab",
+            map: BTreeMap::from([(true, BTreeMap::from([('c', 3)]))]),
+        };
+        let template = Template::from(
+            "{{#pairs}}{{#Both}}<{{.}}>{{/Both}}{{/pairs}} {{name}} {{#bytes}}{{.}}{{/bytes}} {{map.true.c}}"
+        ).unwrap();
+        assert_eq!(template.render(&SerContext::new(&data)), "<1><a><0><> x 9798 3");
+    }
+
+    // fails if serialized, as values not used by templates must not be
+    struct Unused;
+
+    impl Serialize for Unused {
+        fn serialize<S: ser::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+            panic!("unused value serialized")
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Page {
+        title: &'static str,
+        unused: Unused,
+        items: Vec<Unused>,
+    }
+
+    #[test]
+    fn only_values_looked_up_are_serialized() {
+        let page = Page { title: "t", unused: Unused, items: vec![Unused, Unused] };
+        let template = Template::from("{{title}}{{missing}}{{#title}}!{{/title}}").unwrap();
+        assert_eq!(template.render(&SerContext::new(&page)), "t!");
+    }
+
+    struct Failing;
+
+    impl Serialize for Failing {
+        fn serialize<S: ser::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+            Err(ser::Error::custom("cannot serialize"))
+        }
+    }
+
+    #[test]
+    fn serialization_errors_render_as_null() {
+        let data = BTreeMap::from([("ok", vec![1]), ("other", vec![2])]);
+        let context = SerContext::new(&data);
+        let template = Template::from("{{#ok}}{{.}}{{/ok}}").unwrap();
+        assert_eq!(template.render(&context), "1");
+        assert!(context.error().is_none());
+
+        let data = BTreeMap::from([("ok", None), ("failing", Some(Failing))]);
+        let context = SerContext::new(&data);
+        let template = Template::from("[{{failing}}]{{^failing}}null{{/failing}}").unwrap();
+        assert_eq!(template.render(&context), "[]null");
+        assert_eq!(context.error().unwrap().to_string(), "cannot serialize");
+    }
+}
@@ -1,25 +1,124 @@
 use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::io;
+use std::rc::Rc;
+use std::sync::Arc;
 
-use crate::{ContextRef, ContextValue};
+use crate::{ContextRef, ContextValue, Escaper, HtmlEscaper, TemplateError, ErrorKind, RenderError, RenderErrorKind};
 use crate::reader::{Reader, Token};
-use crate::context::Stack;
+use crate::context::{Stack, LoopValue, Produced};
+use crate::ast::{Span, Nodes, Visitor};
+use crate::references::{Reference, References};
+use crate::schema;
+use crate::dependencies::{self, Dependencies};
+use crate::lint::{self, Lint};
+use crate::lambdas::{Lambda, LambdaHelper};
+use crate::filters::{self, Filter, Filters};
 
 /// Represent a compiled Mustache template.
 pub struct Template {
     segments: Segments,
+    // for the content of sections passed to lambdas
+    source: Arc<str>,
 }
 
 impl Template {
     /// Compile a Mustache template.
     /// 
-    /// If the compilation fails, return [Result::Err] with a String giving
-    /// information about the failure (TODO: diagnostics should be improved).
+    /// If the compilation fails, return [Result::Err] with a [TemplateError]
+    /// locating the offending tag in `input`.
     /// 
     /// Otherwise return [Result::Ok] with a [Template] ready to render.
-    pub fn from(input: &str) -> Result<Self, String> {
+    pub fn from(input: &str) -> Result<Self, TemplateError> {
+        Template::compile(input, &CompileOptions::default())
+    }
+
+    /// Compile a Mustache template using [CompileOptions].
+    pub fn compile(input: &str, options: &CompileOptions) -> Result<Self, TemplateError> {
         let mut reader = Reader::new(input);
-        let segments = parse(&mut reader, None)?.0;
-        Ok(Template { segments })
+        reader.set_filters(options.filters);
+        let mut parser = Parser::new(reader, options, false);
+        let segments = parser.parse(None)?.0;
+        Ok(Template { segments, source: Arc::from(input) })
+    }
+
+    /// Check a Mustache template, reporting all errors.
+    /// 
+    /// Unlike [Template::from], compilation does not stop at the first error:
+    /// invalid tags are skipped and mismatched end tags close the enclosing
+    /// section they match (if any), so that a single pass reports every
+    /// problem in `input`. The result is empty if the template compiles,
+    /// errors are otherwise in source order.
+    pub fn check(input: &str) -> Vec<TemplateError> {
+        let options = CompileOptions::default();
+        let mut parser = Parser::new(Reader::new(input), &options, true);
+        match parser.parse(None) {
+            Ok(_) => {
+                // unclosed sections are found last, innermost first
+                let mut errors = parser.errors.unwrap_or_default();
+                errors.sort_by_key(|error| error.offset);
+                errors
+            },
+            Err(error) => vec![error]
+        }
+    }
+
+    /// Look for suspicious constructs in a Mustache template.
+    /// 
+    /// These are not errors, the template may compile and render as
+    /// intended, see [LintKind](crate::LintKind). Errors are reported
+    /// by [Template::check].
+    pub fn lint(input: &str) -> Vec<Lint> {
+        lint::lint(input)
+    }
+
+    /// Nodes of the template, for analysis.
+    pub fn nodes(&self) -> Nodes<'_> {
+        Nodes::new(&self.segments)
+    }
+
+    /// Walk the nodes of the template with a [Visitor].
+    pub fn visit<'t>(&'t self, visitor: &mut dyn Visitor<'t>) {
+        self.nodes().walk(visitor)
+    }
+
+    /// Names used in variable, section, inverted section and partial
+    /// position, in source order.
+    /// 
+    /// Each [Reference] has the name as written and its path within the
+    /// enclosing sections, so that `{{#items}}{{price}}{{/items}}` has
+    /// references to `items` and `items[].price`.
+    pub fn references(&self) -> Vec<Reference> {
+        let mut references = References::default();
+        self.visit(&mut references);
+        references.references
+    }
+
+    /// JSON Schema of the data used by the template, inferred from its
+    /// [references](Template::references).
+    /// 
+    /// Names become object properties, dotted names nested objects. Sections
+    /// with nested names are arrays of objects, and arrays of scalars when
+    /// they use `{{.}}`. A `{{#?name}}` sequence check implies an array and
+    /// a dynamic partial name a string. Other names used in interpolation
+    /// position are scalars, while sections that are only used as conditions
+    /// may be of any type.
+    /// 
+    /// # Sample
+    /// 
+    /// ```
+    /// use mustache::Template;
+    /// use serde_json::json;
+    /// 
+    /// let template = Template::from("{{#items}}{{name}}: {{price.eur}}{{/items}}").unwrap();
+    /// let schema = template.schema();
+    /// assert_eq!(schema["properties"]["items"]["items"]["properties"]["price"], json!({
+    ///     "type": "object",
+    ///     "properties": { "eur": { "type": ["string", "number", "boolean"] } }
+    /// }));
+    /// ```
+    pub fn schema(&self) -> serde_json::Value {
+        schema::schema(&self.references())
     }
 
     /// Render [Template] from data supplied by [ContextRef].
@@ -33,8 +132,10 @@ impl Template {
     /// As there is no [TemplateStore] all partials will result in context
     /// misses, producing no text.
     pub fn render(&self, context: ContextRef) -> String {
-        let mut stack = Stack::new(context);
-        self.render_internal(&mut stack, "", None)
+        let mut result = String::new();
+        // writing to a String never fails
+        let _ = self.render_to(context, &mut result);
+        result
     }
 
     /// Render [Template] using a [ContextRef] and [TemplateStore].
@@ -44,352 +145,862 @@ impl Template {
     pub fn render_with_partials(
         &self, context: ContextRef, partials: &dyn TemplateStore
     ) -> String {
-        let mut stack = Stack::new(context);
-        self.render_internal(&mut stack, "", Some(partials))
+        let mut result = String::new();
+        let _ = self.render_to_with_partials(context, partials, &mut result);
+        result
     }
 
-    pub(crate) fn render_internal(
-        &self, stack: &mut Stack, indent: &str, partials: Option<&dyn TemplateStore>,
-    ) -> String {
-        render_segments(&self.segments, stack, indent, partials)
+    /// Render [Template] using a [ContextRef], an optional [TemplateStore] and [RenderOptions].
+    /// 
+    /// Unlike other render functions, this can fail depending on options.
+    pub fn render_with_options(
+        &self, context: ContextRef, partials: Option<&dyn TemplateStore>, options: &RenderOptions
+    ) -> Result<String, RenderError> {
+        let mut result = String::new();
+        self.render_to_with_options(context, partials, options, &mut result)?;
+        Ok(result)
+    }
+
+    /// Render [Template] from data supplied by [ContextRef] into a [fmt::Write].
+    /// 
+    /// Text is written to `out` as it is produced, without building the
+    /// complete result in memory. The only errors are those reported by `out`.
+    pub fn render_to(&self, context: ContextRef, out: &mut dyn Write) -> fmt::Result {
+        self.render_to_with_options(context, None, &RenderOptions::default(), out)
+            .map_err(|_| fmt::Error)
+    }
+
+    /// Render [Template] using a [ContextRef] and [TemplateStore] into a [fmt::Write].
+    pub fn render_to_with_partials(
+        &self, context: ContextRef, partials: &dyn TemplateStore, out: &mut dyn Write
+    ) -> fmt::Result {
+        self.render_to_with_options(context, Some(partials), &RenderOptions::default(), out)
+            .map_err(|_| fmt::Error)
+    }
+
+    /// Render [Template] using [RenderOptions] into a [fmt::Write].
+    /// 
+    /// Errors reported by `out` are returned as [RenderErrorKind::Write].
+    pub fn render_to_with_options(
+        &self, context: ContextRef, partials: Option<&dyn TemplateStore>, options: &RenderOptions,
+        out: &mut dyn Write
+    ) -> Result<(), RenderError> {
+        let produced = Produced::default();
+        let mut stack = Stack::new(context, &produced);
+        let mut writer = LimitedWriter { inner: out, remaining: options.max_output, exceeded: false };
+        let result = Renderer::new(partials, options, &self.source)
+            .render_segments(&self.segments, &mut stack, "", &mut writer);
+        match result {
+            Err(err) if writer.exceeded => Err(RenderError { kind: RenderErrorKind::OutputLimit, ..err }),
+            result => result
+        }
+    }
+
+    /// Render [Template] from data supplied by [ContextRef] into an [io::Write].
+    /// 
+    /// Text is written to `out` as it is produced, in small pieces: writing to
+    /// a file or socket should go through a [io::BufWriter].
+    /// The first I/O error stops rendering and is returned.
+    pub fn write_to(&self, context: ContextRef, out: &mut dyn io::Write) -> io::Result<()> {
+        self.write_to_with_options(context, None, &RenderOptions::default(), out)
+    }
+
+    /// Render [Template] using a [ContextRef] and [TemplateStore] into an [io::Write].
+    pub fn write_to_with_partials(
+        &self, context: ContextRef, partials: &dyn TemplateStore, out: &mut dyn io::Write
+    ) -> io::Result<()> {
+        self.write_to_with_options(context, Some(partials), &RenderOptions::default(), out)
+    }
+
+    /// Render [Template] using [RenderOptions] into an [io::Write].
+    /// 
+    /// A [RenderError] is returned as an [io::Error] of kind [io::ErrorKind::Other]
+    /// wrapping the [RenderError].
+    pub fn write_to_with_options(
+        &self, context: ContextRef, partials: Option<&dyn TemplateStore>, options: &RenderOptions,
+        out: &mut dyn io::Write
+    ) -> io::Result<()> {
+        let mut writer = IoWriter { inner: out, error: None };
+        self.render_to_with_options(context, partials, options, &mut writer).map_err(|err| {
+            match (&err.kind, writer.error.take()) {
+                (RenderErrorKind::Write, Some(error)) => error,
+                _ => io::Error::other(err)
+            }
+        })
     }
 }
 
-fn parse(
-    reader: &mut Reader<'_>, section: Option<(&str, &str)>
-) -> Result<(Segments, usize), String> {
-    let mut segments = Segments::new();
-    let mut before_tag: usize = 0;
-    while let Some(token) = reader.pop_front() {
-        match token {
-            Token::Text(text, starts_new_line) =>
-                segments.push(
-                    Segment::Text(text.to_owned(), starts_new_line)
-                ),
-            Token::Value(name, is_escaped, starts_new_line) =>
-                segments.push(
-                    Segment::Value(name.to_owned(), is_escaped, starts_new_line)
-                ),
-            Token::Section(name, after_open, is_seqcheck) => {
-                let qualifier = if is_seqcheck { "?" } else { "" };
-                let (od, cd) = reader.delimiters();
-                let (children, before_close) = parse(reader, Some((name, qualifier)))?;
-                segments.push(
-                    Segment::Section(name.to_owned(), after_open, before_close, is_seqcheck, od, cd, children)
-                )
-            },
-            Token::InvertedSection(name) => {
-                let (children, _) = parse(reader, Some((name, "")))?;
-                segments.push(
-                    Segment::InvertedSection(name.to_owned(), children)
-                )
-            },
-            Token::Block(name) => {
-                let (children, _) = parse(reader, Some((name, "")))?;
-                segments.push(
-                    Segment::Block(name.to_owned(), children)
-                )
-            },
-            Token::Parent(name, is_dynamic, indent) => {
-                let qualifier = if is_dynamic { "*" } else { "" };
-                let (children, _) = parse(reader, Some((name, qualifier)))?;
-                let parameters = children
-                    .into_iter()
-                    .filter_map(|segment|
-                        match segment {
-                            Segment::Block(name, children) => Some((name, children)),
-                            _ => None
-                        }
-                    ).collect::<HashMap<_, _>>();
-                segments.push(
-                    Segment::Partial(name.to_owned(), indent.to_owned(), is_dynamic, Some(parameters))
-                )
+/// Options controlling the compilation of a [Template].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompileOptions {
+    /// Accept sections, inverted sections, blocks and parents that are
+    /// still open at end of input, closing them implicitly.
+    /// 
+    /// This is intended for legacy templates. When false (the default) an
+    /// unclosed tag is a [ErrorKind::UnclosedSection] error.
+    pub lenient: bool,
+
+    /// Accept filter chains in interpolation tags, as in
+    /// `{{name | upper | truncate:20}}` or `{{{html | default:"<br>"}}}`.
+    /// 
+    /// Filters are separated by `|`, and their arguments by `:`, double
+    /// quotes keeping separators and whitespace in arguments. They are
+    /// applied in order to the value before escaping, a missing value
+    /// being empty. See [Filters](crate::Filters) for the built-in filters.
+    /// 
+    /// This is an extension of the Mustache specification, disabled by
+    /// default: `|` is then part of the name.
+    pub filters: bool,
+}
+
+struct Parser<'a, 'o> {
+    reader: Reader<'a>,
+    options: &'o CompileOptions,
+    // tags opened and not yet closed, outermost first
+    open: Vec<(&'a str, &'a str)>,
+    // errors collected when recovering, None when stopping at first error
+    errors: Option<Vec<TemplateError>>,
+    // end tag closing a section enclosing the one where it was found
+    pending: Option<Token<'a>>,
+    unwinding: bool,
+    // offset, line and start of line of the last located token
+    position: (usize, usize, usize),
+}
+
+impl<'a, 'o> Parser<'a, 'o> {
+    fn new(reader: Reader<'a>, options: &'o CompileOptions, recover: bool) -> Self {
+        Parser {
+            reader,
+            options,
+            open: Vec::new(),
+            errors: if recover { Some(Vec::new()) } else { None },
+            pending: None,
+            unwinding: false,
+            position: (0, 1, 0),
+        }
+    }
+
+    // span of the current token, located incrementally from the previous one
+    fn span(&mut self) -> Span {
+        let (start, end) = self.reader.span();
+        let input = self.reader.input();
+        let (offset, mut line, mut start_of_line) = match self.position {
+            (offset, _, _) if offset > start => (0, 1, 0),
+            position => position
+        };
+        let skipped = &input[offset..start];
+        if let Some(p) = skipped.rfind('\n') {
+            line += skipped.matches('\n').count();
+            start_of_line = offset + p + 1;
+        }
+        self.position = (start, line, start_of_line);
+        let column = input[start_of_line..start].chars().count() + 1;
+        Span { start, end, line, column }
+    }
+
+    fn pop_front(&mut self) -> Option<Token<'a>> {
+        self.pending.take().or_else(|| self.reader.pop_front())
+    }
+
+    // record the error when recovering, otherwise abort parsing
+    fn fail(&mut self, kind: ErrorKind, span: (usize, usize)) -> Result<(), TemplateError> {
+        let error = TemplateError::new(kind, self.reader.input(), span);
+        match &mut self.errors {
+            Some(errors) => {
+                errors.push(error);
+                Ok(())
             },
-            Token::EndSection(name, qualifier, pos) => {
-                if section != Some((name, qualifier)) {
-                   return Err(format!("unexpected end of section {}", name));
+            None => Err(error)
+        }
+    }
+
+    // parse the content of a tag (name, qualifier) opened at span
+    fn parse_section(
+        &mut self, name: &'a str, qualifier: &'a str, span: Span
+    ) -> Result<(Segments, usize), TemplateError> {
+        self.open.push((name, qualifier));
+        let result = self.parse(Some(span));
+        self.open.pop();
+        result
+    }
+
+    // section is the span of the tag opening the segments being parsed
+    fn parse(&mut self, section: Option<Span>) -> Result<(Segments, usize), TemplateError> {
+        let mut segments = Segments::new();
+        while let Some(token) = self.pop_front() {
+            match token {
+                Token::Text(text, starts_new_line) =>
+                    segments.push(
+                        Segment::Text(text.to_owned(), starts_new_line, self.span())
+                    ),
+                Token::Value(name, is_escaped, starts_new_line) =>
+                    segments.push(
+                        Segment::Value(name.to_owned(), Vec::new(), is_escaped, starts_new_line, self.span())
+                    ),
+                Token::FilteredValue(name, filters, is_escaped, starts_new_line) =>
+                    segments.push(
+                        Segment::Value(name.to_owned(), filters, is_escaped, starts_new_line, self.span())
+                    ),
+                Token::Section(name, after_open, is_seqcheck) => {
+                    let qualifier = if is_seqcheck { "?" } else { "" };
+                    let (od, cd) = self.reader.delimiters();
+                    let span = self.span();
+                    let (children, before_close) = self.parse_section(name, qualifier, span)?;
+                    segments.push(
+                        Segment::Section(name.to_owned(), after_open, before_close, is_seqcheck, od, cd, children, span)
+                    )
+                },
+                Token::InvertedSection(name) => {
+                    let span = self.span();
+                    let (children, _) = self.parse_section(name, "", span)?;
+                    segments.push(
+                        Segment::InvertedSection(name.to_owned(), children, span)
+                    )
+                },
+                Token::Block(name) => {
+                    let span = self.span();
+                    let (children, _) = self.parse_section(name, "", span)?;
+                    segments.push(
+                        Segment::Block(name.to_owned(), children, span)
+                    )
+                },
+                Token::Parent(name, is_dynamic, indent) => {
+                    let qualifier = if is_dynamic { "*" } else { "" };
+                    let span = self.span();
+                    let (children, _) = self.parse_section(name, qualifier, span)?;
+                    let parameters = children
+                        .into_iter()
+                        .filter(|segment| matches!(segment, Segment::Block(..)))
+                        .collect::<Segments>();
+                    segments.push(
+                        Segment::Partial(name.to_owned(), indent.to_owned(), is_dynamic, Some(Arc::new(parameters)), span)
+                    )
+                },
+                Token::EndSection(name, qualifier, pos) => {
+                    let depth = self.open.len();
+                    if depth > 0 && self.open[depth - 1] == (name, qualifier) {
+                        self.unwinding = false;
+                        return Ok((segments, pos));
+                    }
+                    // a mismatched end tag is reported once, in the innermost section
+                    if !self.unwinding {
+                        self.fail(ErrorKind::UnexpectedEndSection, self.reader.span())?;
+                    }
+                    if depth > 1 && self.open[..depth - 1].contains(&(name, qualifier)) {
+                        // close the current section and let an enclosing one match
+                        self.pending = Some(token);
+                        self.unwinding = true;
+                        return Ok((segments, pos));
+                    }
+                },
+                Token::Partial(name, is_dynamic, indent) =>
+                    segments.push(
+                        Segment::Partial(name.to_owned(), indent.to_owned(), is_dynamic, None, self.span())
+                    ),
+                Token::Delimiters(od, cd) => {
+                    self.reader.set_delimiters(od, cd);
+                },
+                Token::Comment(_) => {
+                },
+                Token::Error(kind) => {
+                    self.fail(kind, self.reader.span())?;
                 }
-                before_tag = pos;
-                break;
-            },
-            Token::Partial(name, is_dynamic, indent) =>
-                segments.push(
-                    Segment::Partial(name.to_owned(), indent.to_owned(), is_dynamic, None)
-                ),
-            Token::Delimiters(od, cd) => {
-                reader.set_delimiters(od, cd);
-            },
-            Token::Comment(_) => {
-            },
-            Token::Error(error) => {
-                return Err(format!("reader error: {}", error));
             }
         }
+        if let Some(span) = section {
+            if !self.options.lenient {
+                self.fail(ErrorKind::UnclosedSection, (span.start, span.end))?;
+            }
+        }
+        Ok((segments, self.reader.input().len()))
     }
-    Ok((segments, before_tag))
 }
 
 
-#[derive(Clone)]
-enum Segment {
-    Text(String, bool),
-    Value(String, bool, bool),
-    Section(String, usize, usize, bool, String, String, Segments),
-    InvertedSection(String, Segments),
-    Block(String, Segments),
-    Partial(String, String, bool, Option<HashMap<String, Segments>>),
+pub(crate) enum Segment {
+    Text(String, bool, Span),
+    Value(String, Vec<Filter>, bool, bool, Span),
+    Section(String, usize, usize, bool, String, String, Segments, Span),
+    InvertedSection(String, Segments, Span),
+    Block(String, Segments, Span),
+    // parents have the blocks they override, in source order
+    Partial(String, String, bool, Option<Arc<Segments>>, Span),
+}
+
+pub(crate) type Segments = Vec<Segment>;
+
+// content of the last block with a name in the blocks of a parent
+fn find_block<'s>(blocks: &'s Segments, name: &str) -> Option<&'s Segments> {
+    blocks.iter().rev().find_map(|segment| match segment {
+        Segment::Block(block, children, _) if block == name => Some(children),
+        _ => None
+    })
+}
+
+
+
+/// Options controlling the rendering of a [Template].
+#[derive(Clone, Copy)]
+pub struct RenderOptions<'a> {
+    /// Fail on names that cannot be resolved.
+    /// 
+    /// When true, rendering stops with a [RenderError] if a name in
+    /// interpolation, section or inverted section position is not found
+    /// in the context, or if a partial is not found in the [TemplateStore].
+    /// Otherwise (the default) misses are handled as per Mustache specification.
+    pub strict: bool,
+
+    /// Escaping applied to `{{name}}` interpolation, [HtmlEscaper] by default.
+    pub escaper: &'a dyn Escaper,
+
+    /// Number of lambda results kept compiled during a render, 64 by default.
+    /// 
+    /// Templates produced by lambdas are compiled once per distinct text
+    /// and delimiters, the least recently used being dropped when the cache
+    /// is full. Zero disables the cache.
+    pub lambda_cache_size: usize,
+
+    /// Maximum nesting of partials and lambda results, unlimited by default.
+    /// 
+    /// This protects against recursive partials or lambdas that would
+    /// otherwise overflow the stack, rendering stops with a
+    /// [RenderErrorKind::DepthLimit] error when exceeded. Templates and
+    /// lambdas that are not trusted should be rendered with a limit.
+    pub max_depth: Option<usize>,
+
+    /// Maximum size of the output in bytes, unlimited by default.
+    /// 
+    /// Rendering stops with a [RenderErrorKind::OutputLimit] error when
+    /// exceeded, the text that would exceed the limit not being written.
+    pub max_output: Option<usize>,
+
+    /// Maximum number of section iterations in a render, unlimited by default.
+    /// 
+    /// Rendering stops with a [RenderErrorKind::IterationLimit] error
+    /// when exceeded.
+    pub max_iterations: Option<usize>,
+
+    /// Filters used by templates compiled with [CompileOptions::filters],
+    /// in addition to the built-in ones, none by default.
+    /// 
+    /// Unknown filters leave the value unchanged, or stop rendering with
+    /// a [RenderErrorKind::UnknownFilter] error in strict mode.
+    pub filters: Option<&'a Filters>,
+
+    /// Resolve loop metadata names in sections over sequences, false by default.
+    /// 
+    /// When true, `@index` and `@index1` are the position of the current
+    /// item (from 0 and 1), `@first` and `@last` are booleans, and `@length`
+    /// is the number of items, for the innermost sequence being iterated.
+    /// They can be used in interpolation position, and in section or
+    /// inverted section position as conditions, `@index` being falsy for
+    /// the first item. Outside of sequences, these names are looked up in
+    /// the context as usual.
+    /// 
+    /// This is an extension of the Mustache specification, see the
+    /// `~loop-metadata` module in the tests.
+    pub loop_metadata: bool,
 }
 
-type Segments = Vec<Segment>;
-
-
-fn render_segment(
-    segment: &Segment,
-    stack: &mut Stack, indent: &str, partials: Option<&dyn TemplateStore>
-) -> String {
-    match segment {
-        Segment::Text(text, starts_new_line) =>
-            render_text(
-                text, *starts_new_line,
-                indent
-            ),
-        Segment::Value(name, is_escaped, starts_new_line) =>
-            render_value(
-                name, *is_escaped, *starts_new_line,
-                stack, indent, partials
-            ),
-        Segment::Section(name, start, end, is_seqcheck, od, cd, children) =>
-            render_section(
-                name, *is_seqcheck, od, cd, children, *start, *end,
-                stack, indent, partials
-            ),
-        Segment::InvertedSection(name, children) =>
-            render_inverted_section(
-                name, children,
-                stack, indent, partials
-            ),
-        Segment::Block(_, segments) =>
-            render_segments(
-                segments,
-                stack, indent, partials
-            ),
-        Segment::Partial(name, children_indent, is_dynamic, parameters) =>
-            render_partial(
-                name, children_indent, *is_dynamic, parameters,
-                stack, indent, partials
-            )
+impl Default for RenderOptions<'_> {
+    fn default() -> Self {
+        RenderOptions {
+            strict: false,
+            escaper: &HtmlEscaper,
+            lambda_cache_size: 64,
+            max_depth: None,
+            max_output: None,
+            max_iterations: None,
+            filters: None,
+            loop_metadata: false,
+        }
     }
 }
 
-fn render_text(
-    text: &str, starts_new_line: bool,
-    indent: &str
-) -> String {
-    if indent.is_empty() {
-        text.to_owned()
-    } else {
-        let mut result = String::new();
-        if starts_new_line {
-            result.push_str(indent);
-        }
-        result.push_str(text);
-        if let Some(idx) = text.find('\n') {
-            if idx < text.len() - 1 {
-                // eol inside text requires indenting next line
-                let indent_after = "\n".to_owned() + indent;
-                result = result.replace("\n", &indent_after);
-                if text.ends_with("\n") {
-                    // trailing eol should not indent next line
-                    result.truncate(result.len() - indent.len());
-                }
+
+pub(crate) struct Renderer<'r> {
+    partials: Option<&'r dyn TemplateStore>,
+    options: &'r RenderOptions<'r>,
+    // name of the partial being rendered, None for the top level template
+    template: Option<String>,
+    // block overrides of the parents being rendered, outermost first,
+    // with the source of the template where they are defined
+    blocks: Vec<(Arc<Segments>, Arc<str>)>,
+    lambdas: LambdaCache,
+    // nesting of partials and lambda results, and section iterations so far
+    depth: usize,
+    iterations: usize,
+    // source of the template or lambda result being rendered
+    source: Arc<str>,
+}
+
+impl<'r> Renderer<'r> {
+    fn new(partials: Option<&'r dyn TemplateStore>, options: &'r RenderOptions<'r>, source: &Arc<str>) -> Self {
+        Renderer {
+            partials,
+            options,
+            source: Arc::clone(source),
+            template: None,
+            blocks: Vec::new(),
+            lambdas: LambdaCache::new(options.lambda_cache_size),
+            depth: 0,
+            iterations: 0,
+        }
+    }
+
+    fn render_segment(
+        &mut self, segment: &Segment,
+        stack: &mut Stack, indent: &str, out: &mut dyn Write
+    ) -> Result<(), RenderError> {
+        match segment {
+            Segment::Text(text, starts_new_line, _) =>
+                render_text(
+                    text, *starts_new_line,
+                    indent, out
+                ).map_err(RenderError::from),
+            Segment::Value(name, filters, is_escaped, starts_new_line, span) =>
+                self.render_value(
+                    name, filters, *is_escaped, *starts_new_line, span,
+                    stack, indent, out
+                ),
+            Segment::Section(name, start, end, is_seqcheck, od, cd, children, span) =>
+                self.render_section(
+                    name, *is_seqcheck, (od, cd), children, (*start, *end), span,
+                    stack, indent, out
+                ),
+            Segment::InvertedSection(name, children, span) =>
+                self.render_inverted_section(
+                    name, children, span,
+                    stack, indent, out
+                ),
+            Segment::Block(name, segments, _) =>
+                self.render_block(
+                    name, segments,
+                    stack, indent, out
+                ),
+            Segment::Partial(name, children_indent, is_dynamic, parameters, span) =>
+                self.render_partial(
+                    name, children_indent, *is_dynamic, parameters, span,
+                    stack, indent, out
+                )
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_value(
+        &mut self, name: &str, filters: &[Filter], is_escaped: bool, starts_new_line: bool, span: &Span,
+        stack: &mut Stack, indent: &str, out: &mut dyn Write
+    ) -> Result<(), RenderError> {
+        if starts_new_line && !indent.is_empty() {
+            out.write_str(indent)?;
+        }
+        if let Some(value) = self.loop_value(name, stack) {
+            let value = self.filter(value.text(), filters, span)?;
+            return self.write_value(&value, is_escaped, out);
+        }
+        let found = stack.find(name);
+        let computed = if found.is_none() { stack.computed(name) } else { None };
+        let context = found.or(computed.as_deref());
+        if let Some(helper) = context.and_then(|context| context.lambda()).and_then(Lambda::helper_fn) {
+            if filters.is_empty() {
+                return helper(&mut LambdaHelper::new(self, stack, None, &Segments::new(), indent, out));
             }
+            // the filtered text is written as is, the helper having escaped it
+            let mut text = String::new();
+            helper(&mut LambdaHelper::new(self, stack, None, &Segments::new(), indent, &mut text))?;
+            out.write_str(&self.filter(text, filters, span)?)?;
+            return Ok(());
         }
-        result
+        let value = context.map(|context| match context.lambda() {
+            Some(lambda) => ContextValue::Template(lambda.call(None)),
+            None => context.value()
+        });
+        let value = match value {
+            Some(ContextValue::Text(text)) => text,
+            Some(ContextValue::Template(template)) => {
+                let mut text = String::new();
+                self.render_template(&template, None, name, span, stack, indent, &mut text)?;
+                text
+            },
+            None if self.options.strict => {
+                return Err(self.missing(RenderErrorKind::MissingVariable, name, stack, span));
+            },
+            None if filters.is_empty() => return Ok(()),
+            None => String::new()
+        };
+        let value = self.filter(value, filters, span)?;
+        self.write_value(&value, is_escaped, out)
+    }
+
+    pub(crate) fn escaper(&self) -> &dyn Escaper {
+        self.options.escaper
     }
-}
 
-fn render_value(
-    name: &str, is_escaped: bool, starts_new_line: bool,
-    stack: &mut Stack, indent: &str, partials: Option<&dyn TemplateStore>
-) -> String {
-    let value = if starts_new_line && !indent.is_empty() {
-        let mut value = indent.to_owned();
-        let text = match stack.get(name) {
-            Some(ContextValue::Text(text)) => text.to_owned(),
-            Some(ContextValue::Template(template)) => render_template(&template, None, stack, indent, partials),
-            _ => "".to_owned()
+    fn write_value(&self, value: &str, is_escaped: bool, out: &mut dyn Write) -> Result<(), RenderError> {
+        match is_escaped {
+            true => self.options.escaper.escape(value, out)?,
+            false => out.write_str(value)?
         };
-        value.push_str(&text);
-        value
-    } else {
-        match stack.get(name) {
-            Some(ContextValue::Text(text)) => text.to_owned(),
-            Some(ContextValue::Template(template)) => render_template(&template, None, stack, indent, partials),
-            _ => "".to_owned()
+        Ok(())
+    }
+
+    fn loop_value(&self, name: &str, stack: &mut Stack) -> Option<LoopValue> {
+        if self.options.loop_metadata && name.starts_with('@') {
+            stack.loop_value(name)
+        } else {
+            None
         }
-    };
-    match is_escaped {
-        true => html_escape(value),
-        false => value
     }
-}
 
-fn render_section(
-    name: &str, is_seqcheck: bool, od: &str, cd: &str, children: &Segments, start: usize, end: usize,
-    stack: &mut Stack, indent: &str, partials: Option<&dyn TemplateStore>
-) -> String {
-    let mut result = String::new();
-    let len = stack.len();
-    if stack.push(name, Some((start, end))) {
-        if is_seqcheck {
-            let must_render = stack.in_sequence() && stack.current().is_some();
-            stack.truncate(len);
-            if must_render {
-                result.push_str(&render_segments(children, stack, indent, partials));
+    // apply filters in order, unknown ones failing in strict mode
+    fn filter(&self, mut value: String, filters: &[Filter], span: &Span) -> Result<String, RenderError> {
+        for filter in filters {
+            match filters::apply(self.options.filters, filter, &value) {
+                Some(filtered) => value = filtered,
+                None if self.options.strict => {
+                    return Err(self.error(RenderErrorKind::UnknownFilter, filter.name(), filter.name(), span));
+                },
+                None => ()
             }
-        } else if let Some(ContextValue::Template(template)) = stack.value() {
-            let delimiters = Some((od, cd));
-            result.push_str(
-                &render_template(&template, delimiters, stack, indent, partials)
-            );
-        } else if stack.in_sequence() || !stack.is_falsy() {
-            while stack.current().is_some() {
-                result.push_str(&render_segments(children, stack, indent, partials));
-                stack.next();
-            };
-            stack.truncate(len);
         }
+        Ok(value)
     }
-    result
-}
 
-fn render_template(
-    template: &str, delimiters: Option<(&str, &str)>,
-    stack: &mut Stack, indent: &str, partials: Option<&dyn TemplateStore>
-) -> String {
-    let mut reader = Reader::new(template);
-    if let Some((od, cd)) = delimiters {
-        reader.set_delimiters(od, cd);
-    };
-    match parse(&mut reader, None) {
-        Ok((segments, _)) => render_segments(&segments, stack, indent, partials),
-        Err(_) => "".to_owned()
+    #[allow(clippy::too_many_arguments)]
+    fn render_section(
+        &mut self, name: &str, is_seqcheck: bool, delimiters: (&str, &str), children: &Segments,
+        location: (usize, usize), span: &Span,
+        stack: &mut Stack, indent: &str, out: &mut dyn Write
+    ) -> Result<(), RenderError> {
+        // loop metadata are conditions, never sequences
+        if let Some(value) = self.loop_value(name, stack) {
+            if !is_seqcheck && !value.is_falsy() {
+                self.render_segments(children, stack, indent, out)?;
+            }
+            return Ok(());
+        }
+        let len = stack.len();
+        if stack.push(name, Some(location)) {
+            if is_seqcheck {
+                let must_render = stack.in_sequence() && stack.current().is_some();
+                stack.truncate(len);
+                if must_render {
+                    self.render_segments(children, stack, indent, out)?;
+                }
+            } else if let Some(lambda) = stack.current().and_then(|context| context.lambda()) {
+                let source = Arc::clone(&self.source);
+                let section = &source[location.0..location.1];
+                stack.truncate(len);
+                match lambda.helper_fn() {
+                    Some(helper) =>
+                        helper(&mut LambdaHelper::new(self, stack, Some(section), children, indent, out))?,
+                    None => {
+                        let template = lambda.call(Some(section));
+                        self.render_template(&template, Some(delimiters), name, span, stack, indent, out)?;
+                    }
+                }
+            } else if let Some(ContextValue::Template(template)) = stack.value() {
+                self.render_template(&template, Some(delimiters), name, span, stack, indent, out)?;
+            } else if stack.in_sequence() || !stack.is_falsy() {
+                while stack.current().is_some() {
+                    self.iterations += 1;
+                    if self.options.max_iterations.is_some_and(|max| self.iterations > max) {
+                        return Err(self.error(RenderErrorKind::IterationLimit, name, name, span));
+                    }
+                    self.render_segments(children, stack, indent, out)?;
+                    stack.next();
+                };
+                stack.truncate(len);
+            }
+        } else if let Some(computed) = stack.computed(name) {
+            // computed values are conditions, never sequences
+            if !is_seqcheck && !computed.is_falsy() {
+                self.render_segments(children, stack, indent, out)?;
+            }
+        } else if self.options.strict {
+            return Err(self.missing(RenderErrorKind::MissingSection, name, stack, span));
+        }
+        Ok(())
     }
-}
 
-fn render_inverted_section(
-    name: &str, children: &Segments,
-    stack: &mut Stack, indent: &str, partials: Option<&dyn TemplateStore>
-) -> String {
-    let len = stack.len();
-    let pushed = stack.push(name, None);
-    let must_render = !pushed || stack.is_falsy() || stack.current().is_none();
-    stack.truncate(len);
-    if must_render {
-        render_segments(children, stack, indent, partials)
-    } else {
-        "".to_owned()
-    }    
-}
+    #[allow(clippy::too_many_arguments)]
+    fn render_template(
+        &mut self, template: &str, delimiters: Option<(&str, &str)>, name: &str, span: &Span,
+        stack: &mut Stack, indent: &str, out: &mut dyn Write
+    ) -> Result<(), RenderError> {
+        match self.lambdas.get(template, delimiters) {
+            // blocks in lambda results are not overridden
+            Some(segments) => self.nested(name, span, |renderer| {
+                let source = std::mem::replace(&mut renderer.source, Arc::from(template));
+                let result = renderer.with_blocks(Vec::new(), |renderer| {
+                    renderer.render_segments(&segments, stack, indent, out)
+                });
+                renderer.source = source;
+                result
+            }),
+            None => Ok(())
+        }
+    }
+
+    // render partials and lambda results within the depth limit
+    fn nested<F>(&mut self, name: &str, span: &Span, render: F) -> Result<(), RenderError>
+    where F: FnOnce(&mut Self) -> Result<(), RenderError> {
+        if self.options.max_depth.is_some_and(|max| self.depth >= max) {
+            return Err(self.error(RenderErrorKind::DepthLimit, name, name, span));
+        }
+        self.depth += 1;
+        let result = render(self);
+        self.depth -= 1;
+        result
+    }
 
-fn render_partial(
-    name: &str, children_indent: &str, is_dynamic: bool, parameters: &Option<HashMap<String, Segments>>,
-    stack: &mut Stack, indent: &str, partials: Option<&dyn TemplateStore>
-) -> String {
-    if let Some(store) = partials {
-        let maybe_template = if is_dynamic {
+    fn render_inverted_section(
+        &mut self, name: &str, children: &Segments, span: &Span,
+        stack: &mut Stack, indent: &str, out: &mut dyn Write
+    ) -> Result<(), RenderError> {
+        if let Some(value) = self.loop_value(name, stack) {
+            return match value.is_falsy() {
+                true => self.render_segments(children, stack, indent, out),
+                false => Ok(())
+            };
+        }
+        let len = stack.len();
+        let pushed = stack.push(name, None);
+        if let Some(computed) = if pushed { None } else { stack.computed(name) } {
+            return match computed.is_falsy() {
+                true => self.render_segments(children, stack, indent, out),
+                false => Ok(())
+            };
+        }
+        if !pushed && self.options.strict {
+            return Err(self.missing(RenderErrorKind::MissingSection, name, stack, span));
+        }
+        let must_render = !pushed || stack.is_falsy() || stack.current().is_none();
+        stack.truncate(len);
+        if must_render {
+            self.render_segments(children, stack, indent, out)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_partial(
+        &mut self, name: &str, children_indent: &str, is_dynamic: bool,
+        parameters: &Option<Arc<Segments>>, span: &Span,
+        stack: &mut Stack, indent: &str, out: &mut dyn Write
+    ) -> Result<(), RenderError> {
+        let name = if is_dynamic {
             match stack.get(name) {
-                Some(ContextValue::Text(name)) => store.get(&name),
-                _ => None
+                Some(ContextValue::Text(name)) => name,
+                None if self.options.strict => {
+                    return Err(self.missing(RenderErrorKind::MissingVariable, name, stack, span));
+                },
+                _ => return Ok(())
             }
         } else {
-            store.get(name)
+            name.to_owned()
         };
+        let maybe_template = self.partials.and_then(|store| store.get(&name));
         if let Some(template) = maybe_template {
             let next_indent = indent.to_owned() + children_indent;
-            if let Some(parameters) = parameters {
-                let segments = substitute(&template.segments, parameters);
-                render_segments(&segments, stack, &next_indent, partials)
-            } else {
-                render_segments(&template.segments, stack, &next_indent, partials)
-            }
+            // a parent adds its blocks to the enclosing overrides, a plain partial does not see them
+            let blocks = match parameters {
+                Some(parameters) => {
+                    let mut blocks = self.blocks.clone();
+                    blocks.push((Arc::clone(parameters), Arc::clone(&self.source)));
+                    blocks
+                },
+                None => Vec::new()
+            };
+            self.nested(&name, span, |renderer| {
+                let parent = renderer.template.replace(name.clone());
+                let source = std::mem::replace(&mut renderer.source, Arc::clone(&template.source));
+                let result = renderer.with_blocks(blocks, |renderer| {
+                    renderer.render_segments(&template.segments, stack, &next_indent, out)
+                });
+                renderer.template = parent;
+                renderer.source = source;
+                result
+            })
+        } else if self.options.strict {
+            Err(self.error(RenderErrorKind::MissingPartial, &name, &name, span))
         } else {
-            "".to_owned()
+            Ok(())
+        }
+    }
+
+    fn render_block(
+        &mut self, name: &str, segments: &Segments,
+        stack: &mut Stack, indent: &str, out: &mut dyn Write
+    ) -> Result<(), RenderError> {
+        // the outermost override wins, its own blocks being overridden by enclosing ones
+        let overridden = self.blocks.iter().position(|(blocks, _)| find_block(blocks, name).is_some());
+        match overridden {
+            Some(idx) => {
+                let (blocks, source) = self.blocks[idx].clone();
+                let enclosing = self.blocks[..idx].to_vec();
+                let source = std::mem::replace(&mut self.source, source);
+                let result = self.with_blocks(enclosing, |renderer| {
+                    let segments = find_block(&blocks, name).unwrap_or(segments);
+                    renderer.render_segments(segments, stack, indent, out)
+                });
+                self.source = source;
+                result
+            },
+            None => self.render_segments(segments, stack, indent, out)
+        }
+    }
+
+    // run render with block overrides, restoring the current ones after
+    fn with_blocks<T>(&mut self, blocks: Vec<(Arc<Segments>, Arc<str>)>, render: impl FnOnce(&mut Self) -> T) -> T {
+        let current = std::mem::replace(&mut self.blocks, blocks);
+        let result = render(self);
+        self.blocks = current;
+        result
+    }
+
+    pub(crate) fn render_segments(
+        &mut self, segments: &Segments,
+        stack: &mut Stack, indent: &str, out: &mut dyn Write
+    ) -> Result<(), RenderError> {
+        for segment in segments {
+            self.render_segment(segment, stack, indent, out)?;
+        }
+        Ok(())
+    }
+
+    fn missing(&self, kind: RenderErrorKind, name: &str, stack: &mut Stack, span: &Span) -> RenderError {
+        let key = stack.missing_key(name);
+        self.error(kind, name, &key, span)
+    }
+
+    fn error(&self, kind: RenderErrorKind, name: &str, key: &str, span: &Span) -> RenderError {
+        RenderError {
+            kind,
+            name: name.to_owned(),
+            key: key.to_owned(),
+            template: self.template.clone(),
+            offset: span.start,
+            line: span.line,
+            column: span.column,
         }
-    } else {
-        "".to_owned()
     }
 }
 
-fn render_segments(
-    segments: &Segments,
-    stack: &mut Stack, indent: &str, partials: Option<&dyn TemplateStore>
-) -> String {
-    segments.iter().fold(String::new(), |mut acc, segment| {
-        acc.push_str(&render_segment(segment, stack, indent, partials));
-        acc
-    })
+fn render_text(
+    text: &str, starts_new_line: bool,
+    indent: &str, out: &mut dyn Write
+) -> fmt::Result {
+    if indent.is_empty() {
+        out.write_str(text)
+    } else {
+        if starts_new_line {
+            out.write_str(indent)?;
+        }
+        let mut lines = text.split_inclusive('\n').peekable();
+        while let Some(line) = lines.next() {
+            out.write_str(line)?;
+            // eol inside text requires indenting next line
+            // trailing eol should not indent next line
+            if line.ends_with('\n') && lines.peek().is_some() {
+                out.write_str(indent)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 
-fn substitute(segments: &Segments, parameters: &HashMap<String, Segments>) -> Segments {
-    segments.iter()
-        .map(|segment|
-            substitute_segment(segment, parameters)
-        ).collect::<Vec<_>>()
+// lambda result text and delimiters
+type LambdaKey = (String, Option<(String, String)>);
+
+// compiled lambda results, bounded in size
+struct LambdaCache {
+    capacity: usize,
+    // segments (None if the text does not compile) and time of last use
+    entries: HashMap<LambdaKey, (Option<Rc<Segments>>, u64)>,
+    clock: u64,
 }
 
-fn substitute_segment(segment: &Segment, parameters: &HashMap<String, Segments>) -> Segment {
-    match segment {
-        Segment::Text(_, _) | Segment::Value(_, _, _) =>
-            segment.clone(),
-        Segment::Section(name, after_open, before_close, is_seqcheck, od, cd, segments) =>
-            Segment::Section(
-                name.to_owned(), *after_open, *before_close, *is_seqcheck, od.clone(), cd.clone(), substitute(segments, parameters)
-            ),
-        Segment::InvertedSection(name, segments) =>
-            Segment::InvertedSection(
-                name.to_owned(), substitute(segments, parameters)
-            ),
-        Segment::Block(name, segments) => {
-            let updated = parameters.get(name).map_or_else(
-                || substitute(segments, parameters),
-                |segments| segments.clone() 
-            );
-            Segment::Block(name.to_owned(), updated)
-        },
-        Segment::Partial(name, indent, is_dynamic, current) => {
-            let updated = current.clone().map(|initial| {
-                initial.into_iter().chain(parameters.clone()).collect()
-            });
-            Segment::Partial(name.to_owned(), indent.to_owned(), *is_dynamic, updated)
+impl LambdaCache {
+    fn new(capacity: usize) -> Self {
+        LambdaCache {
+            capacity,
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    // segments for a lambda result, None if it does not compile
+    fn get(&mut self, template: &str, delimiters: Option<(&str, &str)>) -> Option<Rc<Segments>> {
+        let key = (
+            template.to_owned(),
+            delimiters.map(|(od, cd)| (od.to_owned(), cd.to_owned()))
+        );
+        self.clock += 1;
+        if let Some((segments, last_used)) = self.entries.get_mut(&key) {
+            *last_used = self.clock;
+            return segments.clone();
+        }
+        let segments = compile_lambda(template, delimiters).map(Rc::new);
+        if self.capacity > 0 {
+            if self.entries.len() >= self.capacity {
+                let oldest = self.entries.iter()
+                    .min_by_key(|(_, (_, last_used))| *last_used)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
+                }
+            }
+            self.entries.insert(key, (segments.clone(), self.clock));
         }
+        segments
     }
 }
 
-fn html_escape(input: String) -> String {
-    let needs_escape = input.chars().any(|c| {
-        matches!(c, '&' | '<' | '>' | '"' | '\'' | '/' | '=' | '`')
-    });
-    
-    if !needs_escape {
-        input
-    } else {    
-        let mut escaped = String::with_capacity(input.len());
-        for c in input.chars() {
-            match c {
-                '&' => escaped.push_str("&amp;"),
-                '<' => escaped.push_str("&lt;"),
-                '>' => escaped.push_str("&gt;"),
-                '"' => escaped.push_str("&quot;"),
-                '\'' => escaped.push_str("&#39;"),
-                '/' => escaped.push_str("&#47;"),
-                '=' => escaped.push_str("&#61;"),
-                '`' => escaped.push_str("&#96;"),
-                _ => escaped.push(c),
+fn compile_lambda(template: &str, delimiters: Option<(&str, &str)>) -> Option<Segments> {
+    let mut reader = Reader::new(template);
+    if let Some((od, cd)) = delimiters {
+        reader.set_delimiters(od, cd);
+    };
+    let options = CompileOptions { lenient: true, ..Default::default() };
+    Parser::new(reader, &options, false).parse(None).ok().map(|(segments, _)| segments)
+}
+
+// fmt::Write adapter over io::Write keeping the underlying io::Error
+struct IoWriter<'w> {
+    inner: &'w mut dyn io::Write,
+    error: Option<io::Error>,
+}
+
+impl Write for IoWriter<'_> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.inner.write_all(text.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+
+// fmt::Write adapter failing once the output exceeds a number of bytes
+struct LimitedWriter<'w> {
+    inner: &'w mut dyn Write,
+    remaining: Option<usize>,
+    exceeded: bool,
+}
+
+impl Write for LimitedWriter<'_> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        if let Some(remaining) = &mut self.remaining {
+            if text.len() > *remaining {
+                self.exceeded = true;
+                return Err(fmt::Error);
             }
+            *remaining -= text.len();
         }
-        escaped
+        self.inner.write_str(text)
     }
 }
 
@@ -397,35 +1008,88 @@ fn html_escape(input: String) -> String {
 /// Template resolver
 /// 
 /// This trait is used to retreive compiled [Template] by name.
-pub trait TemplateStore {
-    fn get(&self, name: &str) -> Option<&Template>;
+/// 
+/// Templates are shared rather than borrowed from the store, so that a
+/// store can replace a template (see [HotReloadStore](crate::HotReloadStore))
+/// while a previous version is still being rendered.
+/// 
+/// Stores are [Send] and [Sync] so that they can be shared by threads
+/// rendering concurrently.
+pub trait TemplateStore: Send + Sync {
+    fn get(&self, name: &str) -> Option<Arc<Template>>;
 }
 
 
 /// Pre-compiled [Template] instances.
 pub struct TemplateMap {
-    templates: HashMap<String, Template>,
+    templates: HashMap<String, Arc<Template>>,
 }
 
 impl TemplateMap {
     /// Create a [TemplateMap] for a map of name to Mustache source code.
     /// 
-    /// If any of the Mustache template does not compile the result is a [Result::Err].
-    pub fn new(input: HashMap<&str, &str>) -> Result<Self, String> {
+    /// If any of the Mustache template does not compile the result is a [Result::Err]
+    /// with a [TemplateError] naming the failing template.
+    pub fn new(input: HashMap<&str, &str>) -> Result<Self, TemplateError> {
+        TemplateMap::compile(input, &CompileOptions::default())
+    }
+
+    /// Create a [TemplateMap] compiling sources using [CompileOptions].
+    pub fn compile(input: HashMap<&str, &str>, options: &CompileOptions) -> Result<Self, TemplateError> {
         let mut templates = HashMap::new();
         for (name, text) in input {
-            let template = match Template::from(text) {
+            let template = match Template::compile(text, options) {
                 Ok(template) => template,
-                Err(err) => return Err(format!("{}: {}", name, err))
+                Err(err) => return Err(err.with_name(name))
             };
-            templates.insert(name.to_owned(), template);
+            templates.insert(name.to_owned(), Arc::new(template));
         }
         Ok(TemplateMap { templates })
     }
+
+    /// Check the partials and parents used across templates.
+    /// 
+    /// The result has the dependency graph between templates, and reports
+    /// missing partials, cycles and dynamic names that cannot be checked.
+    pub fn dependencies(&self) -> Dependencies {
+        let templates = self.templates.iter().map(|(name, template)| (name, template.as_ref()));
+        dependencies::dependencies(templates)
+    }
 }
 
 impl TemplateStore for TemplateMap {
-    fn get(&self, name: &str) -> Option<&Template> {
-        self.templates.get(name)
+    fn get(&self, name: &str) -> Option<Arc<Template>> {
+        self.templates.get(name).cloned()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lambda_cache_reuses_segments() {
+        let mut cache = LambdaCache::new(4);
+        let first = cache.get("{{a}}", None).unwrap();
+        assert!(Rc::ptr_eq(&first, &cache.get("{{a}}", None).unwrap()));
+        assert!(!Rc::ptr_eq(&first, &cache.get("{{a}}", Some(("<%", "%>"))).unwrap()));
+        assert!(cache.get("{{a", None).is_none());
+        assert_eq!(cache.entries.len(), 3);
+    }
+
+    #[test]
+    fn lambda_cache_drops_least_recently_used() {
+        let mut cache = LambdaCache::new(2);
+        let a = cache.get("a", None).unwrap();
+        cache.get("b", None);
+        cache.get("a", None);
+        cache.get("c", None);
+        assert!(Rc::ptr_eq(&a, &cache.get("a", None).unwrap()));
+        assert!(!cache.entries.contains_key(&("b".to_owned(), None)));
+
+        let mut cache = LambdaCache::new(0);
+        assert!(cache.get("a", None).is_some());
+        assert!(cache.entries.is_empty());
     }
 }
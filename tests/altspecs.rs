@@ -1,30 +1,60 @@
+// spec results are folded as in specs.rs
+#![allow(clippy::manual_try_fold)]
+
 extern crate mustache;
 use mustache::{
-    Template, TemplateMap,
-    YamlValue, MapsAndLists
+    Template, TemplateMap, RenderOptions,
+    YamlValue, JsonValue, MapsAndLists, Lambdas
 };
 
-use std::{fs, collections::HashMap, cell::RefCell, rc::Rc};
+use std::{fs, collections::HashMap, cell::{Cell, RefCell}, rc::Rc};
 use serde::Deserialize;
 use serde_yaml::Mapping as YamlMapping;
 
 
 #[test]
 fn sequence_check_test() -> Result<(), String> {
-    run_spec_file("~sequence-check", true)
+    run_spec_file("altspecs/~sequence-check", true, render_maps_and_lists)
 }
 
 #[test]
 fn lambdas_test() -> Result<(), String> {
-    run_spec_file("~lambdas", true)
+    run_spec_file("altspecs/~lambdas", true, render_maps_and_lists)
+}
+
+// same cases with lambdas from a registry rather than the data
+#[test]
+fn lambdas_registry_test() -> Result<(), String> {
+    run_spec_file("altspecs/~lambdas", true, render_json_with_lambdas)
+}
+
+// lambda sections in partials and blocks are not sliced from the template,
+// which MapsAndLists::lambda1 requires
+#[test]
+fn lambdas_partials_test() -> Result<(), String> {
+    run_spec_file("altspecs/~lambdas-partials", true, render_json_with_lambdas)
+}
+
+// the optional lambdas module of the official specification, its code
+// being implemented per test
+#[test]
+fn official_lambdas_test() -> Result<(), String> {
+    run_spec_file("specs/~lambdas", true, render_json_with_lambdas)
 }
 
+#[test]
+fn loop_metadata_test() -> Result<(), String> {
+    run_spec_file("altspecs/~loop-metadata", true, render_json_with_loop_metadata)
+}
+
+
+type Render = fn(&YamlTestSpec, &Template, &TemplateMap) -> String;
 
-fn run_spec_file(name: &str, log: bool) -> Result<(), String> {
+fn run_spec_file(name: &str, log: bool, render: Render) -> Result<(), String> {
     yaml_spec(name)?
         .tests.iter().fold(
             Ok(()),
-            |acc, test| match (acc, run_spec_test(test, log)) {
+            |acc, test| match (acc, run_spec_test(test, log, render)) {
                 (acc, Ok(())) => acc,
                 (Ok(()), Err(err)) => Err(format!("specs ({}): {}", name, err)),
                 (Err(err1), Err(err2)) => Err(format!("{}, {}", err1, err2))
@@ -47,17 +77,17 @@ struct YamlTestSpec {
 }
 
 fn yaml_spec(name: &str) -> Result<YamlSpecFile, String> {
-    let path = format!("tests/altspecs/{}.yml", name);
+    let path = format!("tests/{}.yml", name);
     let text = fs::read_to_string(path).map_err(
-        |err| format!("io: {}", err.to_string())
+        |err| format!("io: {}", err)
     )?;
     serde_yaml::from_str::<YamlSpecFile>(&text).map_err(
-        |err| format!("yaml: {}", err.to_string())
+        |err| format!("yaml: {}", err)
     )
 }
 
-fn run_spec_test(test: &YamlTestSpec, log: bool) -> Result<(), String> {
-    let template = Template::from(&test.template)?;
+fn run_spec_test(test: &YamlTestSpec, log: bool, render: Render) -> Result<(), String> {
+    let template = Template::from(&test.template).map_err(|err| err.to_string())?;
     let partials = if let Some(values) = &test.partials {
         values.iter().map(
             |(name, text)| {
@@ -69,14 +99,8 @@ fn run_spec_test(test: &YamlTestSpec, log: bool) -> Result<(), String> {
     } else {
         HashMap::new()
     };
-    let partials = TemplateMap::new(partials)?;
-    let data = maps_and_lists(
-        &test.data,
-        &Rc::from(test.template.as_str())
-    );
-    let result = template.render_with_partials(
-        &data, &partials
-    );
+    let partials = TemplateMap::new(partials).map_err(|err| err.to_string())?;
+    let result = render(test, &template, &partials);
     if result != test.expected {
         if log {
             println!("{}: fail", test.name);
@@ -93,8 +117,83 @@ fn run_spec_test(test: &YamlTestSpec, log: bool) -> Result<(), String> {
 }
 
 
-fn maps_and_lists<'a>(
-    yaml: &'a YamlValue,
+fn render_maps_and_lists(test: &YamlTestSpec, template: &Template, partials: &TemplateMap) -> String {
+    let data = maps_and_lists(
+        &test.data,
+        &Rc::from(test.template.as_str())
+    );
+    template.render_with_partials(&data, partials)
+}
+
+// tagged values at the root are lambdas, the rest is converted to JSON
+fn render_json_with_lambdas(test: &YamlTestSpec, template: &Template, partials: &TemplateMap) -> String {
+    let mut lambdas = Lambdas::new();
+    let mut data = serde_json::Map::new();
+    for (key, value) in test.data.as_mapping().unwrap() {
+        let key = key.as_str().unwrap();
+        match value {
+            YamlValue::Tagged(tv) if tv.tag == "code" => {
+                add_code_lambda(&mut lambdas, key, &test.name);
+            },
+            YamlValue::Tagged(tv) => {
+                let value = tv.value.as_str().unwrap().to_owned();
+                match tv.tag.to_string().as_str() {
+                    "!lambda0_str" => lambdas.lambda0(key, move || value.clone()),
+                    "!lambda1_str" => lambdas.lambda1(key, move |s| value.replace("{}", s)),
+                    "!lambda0_fn" if value == "counter" => {
+                        let counter = Cell::new(0);
+                        lambdas.lambda0(key, move || {
+                            counter.set(counter.get() + 1);
+                            counter.get().to_string()
+                        })
+                    },
+                    "!lambda1_fn" if value == "check_contents" =>
+                        lambdas.lambda1(key, |s| (if s == "{{x}}" { "yes" } else { "no" }).to_owned()),
+                    tag => panic!("unknown lambda {}", tag)
+                };
+            },
+            value => {
+                data.insert(key.to_owned(), serde_json::to_value(value).unwrap());
+            }
+        }
+    }
+    let data = JsonValue::Object(data);
+    template.render_with_partials(&lambdas.overlay(&data), partials)
+}
+
+// lambdas of the official specification, written in other languages
+fn add_code_lambda(lambdas: &mut Lambdas, key: &str, test: &str) {
+    match test {
+        "Interpolation" => lambdas.lambda0(key, || "world".to_owned()),
+        "Interpolation - Expansion" => lambdas.lambda0(key, || "{{planet}}".to_owned()),
+        "Interpolation - Alternate Delimiters" =>
+            lambdas.lambda0(key, || "|planet| => {{planet}}".to_owned()),
+        "Interpolation - Multiple Calls" => {
+            let calls = Cell::new(0);
+            lambdas.lambda0(key, move || {
+                calls.set(calls.get() + 1);
+                calls.get().to_string()
+            })
+        },
+        "Escaping" => lambdas.lambda0(key, || ">".to_owned()),
+        "Section" => lambdas.lambda1(key, |text| (if text == "{{x}}" { "yes" } else { "no" }).to_owned()),
+        "Section - Expansion" => lambdas.lambda1(key, |text| format!("{}{{{{planet}}}}{}", text, text)),
+        "Section - Alternate Delimiters" =>
+            lambdas.lambda1(key, |text| format!("{}{{{{planet}}}} => |planet|{}", text, text)),
+        "Section - Multiple Calls" => lambdas.lambda1(key, |text| format!("__{}__", text)),
+        "Inverted Section" => lambdas.lambda1(key, |_| String::new()),
+        test => panic!("no code for {}", test)
+    };
+}
+
+fn render_json_with_loop_metadata(test: &YamlTestSpec, template: &Template, partials: &TemplateMap) -> String {
+    let data = serde_json::to_value(&test.data).unwrap();
+    let options = RenderOptions { loop_metadata: true, ..Default::default() };
+    template.render_with_options(&data, Some(partials), &options).unwrap()
+}
+
+fn maps_and_lists(
+    yaml: &YamlValue,
     template: &Rc<str>
 ) -> MapsAndLists {
     match yaml {
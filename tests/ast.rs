@@ -0,0 +1,120 @@
+extern crate mustache;
+use mustache::{Template, Node, Nodes, Span, Visitor};
+
+
+// one line per node, indented by depth
+fn outline(nodes: Nodes, depth: usize, lines: &mut Vec<String>) {
+    for node in nodes.iter() {
+        let kind = match node {
+            Node::Text { text, .. } => format!("text {:?}", text),
+            Node::Variable { name, escaped, .. } => format!("variable {} {}", name, escaped),
+            Node::Section { name, .. } => format!("section {}", name),
+            Node::InvertedSection { name, .. } => format!("inverted {}", name),
+            Node::SeqCheckSection { name, .. } => format!("seqcheck {}", name),
+            Node::Block { name, .. } => format!("block {}", name),
+            Node::Partial { name, dynamic, .. } => format!("partial {} {}", name, dynamic),
+            Node::Parent { name, dynamic, .. } => format!("parent {} {}", name, dynamic),
+        };
+        let span = node.span();
+        lines.push(format!("{}{} @{}:{}", "  ".repeat(depth), kind, span.line, span.column));
+        if let Some(children) = node.children() {
+            outline(children, depth + 1, lines);
+        }
+    }
+}
+
+#[test]
+fn nodes_of_all_kinds() {
+    let template = Template::from(concat!(
+        "hi {{name}}{{{raw}}}\n",
+        "{{#items}}\n",
+        "  {{^empty}}{{#?list}}{{>*item}}{{/?list}}{{/empty}}\n",
+        "{{/items}}\n",
+        "{{! comment }}{{<layout}}{{$body}}x{{>footer}}{{/body}}{{/layout}}"
+    )).unwrap();
+    let mut lines = Vec::new();
+    outline(template.nodes(), 0, &mut lines);
+    assert_eq!(lines, [
+        "text \"hi \" @1:1",
+        "variable name true @1:4",
+        "variable raw false @1:12",
+        "text \"\\n\" @1:21",
+        "section items @2:1",
+        "  inverted empty @3:3",
+        "    seqcheck list @3:13",
+        "      partial item true @3:23",
+        "parent layout false @5:15",
+        "  block body @5:26",
+        "    text \"x\" @5:35",
+        "    partial footer false @5:36",
+    ]);
+}
+
+#[test]
+fn spans_and_accessors() {
+    let source = "a\n  {{#s}}{{x.y}}{{/s}}";
+    let template = Template::from(source).unwrap();
+    let nodes = template.nodes();
+    assert_eq!(nodes.len(), 2);
+    let section = nodes.get(1).unwrap();
+    assert_eq!(section.name(), Some("s"));
+    assert_eq!(section.span(), Span { start: 4, end: 10, line: 2, column: 3 });
+    assert_eq!(&source[section.span().start..section.span().end], "{{#s}}");
+
+    let variable = section.children().unwrap().get(0).unwrap();
+    assert_eq!(variable.name(), Some("x.y"));
+    assert!(variable.children().is_none());
+    assert_eq!(&source[variable.span().start..variable.span().end], "{{x.y}}");
+    assert!(nodes.get(2).is_none());
+}
+
+#[test]
+fn visitor_enters_and_leaves() {
+    struct Trace(Vec<String>);
+
+    impl<'t> Visitor<'t> for Trace {
+        fn enter(&mut self, node: &Node<'t>) -> bool {
+            self.0.push(format!("+{}", node.name().unwrap_or("text")));
+            !matches!(node, Node::InvertedSection { .. })
+        }
+
+        fn leave(&mut self, node: &Node<'t>) {
+            self.0.push(format!("-{}", node.name().unwrap_or("text")));
+        }
+    }
+
+    let template = Template::from("{{#a}}{{b}}{{/a}}{{^c}}{{d}}{{/c}}.").unwrap();
+    let mut trace = Trace(Vec::new());
+    template.visit(&mut trace);
+    assert_eq!(trace.0, ["+a", "+b", "-b", "-a", "+c", "-c", "+text", "-text"]);
+}
+
+#[test]
+fn references_keep_section_paths() {
+    let template = Template::from(concat!(
+        "{{title}} {{user.name}}\n",
+        "{{#items}}{{price}} {{.}} {{#tags}}{{.}}{{/tags}}{{^sold}}{{stock}}{{/sold}}{{/items}}\n",
+        "{{#?items}}{{count}}{{/?items}}{{>row}}{{>*kind}}{{<layout}}{{$b}}{{body}}{{/b}}{{/layout}}"
+    )).unwrap();
+    let references = template.references().into_iter()
+        .map(|reference| format!("{} {} {}", reference.kind, reference.path, reference.span.column))
+        .collect::<Vec<_>>();
+    assert_eq!(references, [
+        "variable title 1",
+        "variable user.name 11",
+        "section items 1",
+        "variable items[].price 11",
+        "variable items[] 21",
+        "section items[].tags 27",
+        "variable items[].tags[] 36",
+        "inverted section items[].sold 50",
+        "variable items[].stock 59",
+        "sequence check items 1",
+        "variable count 12",
+        "partial row 32",
+        "dynamic partial kind 40",
+        "partial layout 50",
+        "variable body 67",
+    ]);
+    assert_eq!(template.references()[1].name, "user.name");
+}
@@ -0,0 +1,187 @@
+use std::{fs, io::Write, process::{Command, Output, Stdio}};
+
+mod common;
+use common::directory;
+
+
+fn mustache(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mustache"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn text(bytes: &[u8]) -> &str {
+    std::str::from_utf8(bytes).unwrap()
+}
+
+
+#[test]
+fn renders_file_with_yaml_data_and_partials() {
+    let root = directory("render", &[
+        ("page.mustache", "{{#hosts}}{{>host}}{{/hosts}}"),
+        ("data.yml", "hosts:\n  - name: a\n    port: 80\n  - name: b\n    port: 81\n"),
+        ("partials/host.mustache", "{{name}}:{{port}}\n"),
+    ]);
+    let output = mustache(&[
+        "render", root.join("page.mustache").to_str().unwrap(),
+        "--data", root.join("data.yml").to_str().unwrap(),
+        "--partials", root.join("partials").to_str().unwrap(),
+    ], "");
+    assert_eq!(text(&output.stderr), "");
+    assert!(output.status.success());
+    assert_eq!(text(&output.stdout), "a:80\nb:81\n");
+}
+
+#[test]
+fn reads_template_from_stdin_and_writes_output_file() {
+    let root = directory("stdin", &[("data.json", r#"{"name": "x & y"}"#)]);
+    let out = root.join("out.txt");
+    let output = mustache(&[
+        "render", "-d", root.join("data.json").to_str().unwrap(), "-o", out.to_str().unwrap()
+    ], "{{name}} {{{name}}}");
+    assert!(output.status.success());
+    assert_eq!(text(&output.stdout), "");
+    assert_eq!(fs::read_to_string(out).unwrap(), "x &amp; y x & y");
+}
+
+#[test]
+fn reads_data_from_stdin() {
+    let root = directory("data", &[("t.mustache", "{{a}}")]);
+    let output = mustache(&["render", root.join("t.mustache").to_str().unwrap(), "-d", "-"], "a: 1");
+    assert_eq!(text(&output.stdout), "1");
+}
+
+#[test]
+fn reports_positioned_errors() {
+    let root = directory("errors", &[
+        ("bad.mustache", "ok\n {{#a}}"),
+        ("good.mustache", "{{>p}}{{x}}"),
+        ("partials/p.mustache", "{{/b}}"),
+    ]);
+    let bad = root.join("bad.mustache");
+    let output = mustache(&["render", bad.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(text(&output.stderr), format!("mustache: {}:2:2: unclosed section `{{{{#a}}}}`\n", bad.display()));
+
+    let good = root.join("good.mustache");
+    let partials = root.join("partials");
+    let output = mustache(&["render", good.to_str().unwrap(), "-p", partials.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(1));
+    let expected = format!("{}:1:1: unexpected end of section", partials.join("p.mustache").display());
+    assert!(text(&output.stderr).contains(&expected), "{}", text(&output.stderr));
+
+    let output = mustache(&["render", "--strict"], "a\n{{x}}");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(text(&output.stderr), "mustache: <stdin>:2:1: missing variable `x`\n");
+    // the text before the error has been written
+    assert_eq!(text(&output.stdout), "a\n");
+}
+
+#[test]
+fn reports_only_broken_partials_that_are_rendered() {
+    let root = directory("unused", &[
+        ("t.mustache", "{{>used}}{{#b}}{{>unused}}{{/b}}"),
+        ("partials/used.mustache", "{{#a}}{{>nested}}{{/a}}"),
+        ("partials/nested.mustache", "[{{a}}]"),
+        ("partials/unused.mustache", "{{#a}}"),
+    ]);
+    let (template, partials) = (root.join("t.mustache"), root.join("partials"));
+    let args = ["render", template.to_str().unwrap(), "-p", partials.to_str().unwrap(), "-d", "-"];
+    let output = mustache(&args, "a: 1");
+    assert_eq!(text(&output.stderr), "");
+    assert!(output.status.success());
+    assert_eq!(text(&output.stdout), "[1]");
+
+    let output = mustache(&args, "a: 1\nb: true");
+    assert_eq!(output.status.code(), Some(1));
+    let expected = format!("{}:1:1: unclosed section", partials.join("unused.mustache").display());
+    assert!(text(&output.stderr).contains(&expected), "{}", text(&output.stderr));
+}
+
+#[test]
+fn rejects_invalid_arguments() {
+    for args in [&[][..], &["draw"], &["render", "--data"], &["render", "a", "b"], &["render", "--what"]] {
+        let output = mustache(args, "");
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(text(&output.stderr).contains("Usage: mustache render"));
+    }
+    let output = mustache(&["render", "-", "--data", "-"], "");
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = mustache(&["render", "missing.mustache"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(text(&output.stderr).starts_with("mustache: missing.mustache: "));
+}
+
+#[test]
+fn check_reports_errors_in_directories() {
+    let root = directory("check", &[
+        ("ok.mustache", "{{#a}}{{/a}}{{>missing}}"),
+        ("sub/bad.mustache", "{{#a}}\n{{/b}}{{x"),
+    ]);
+    let output = mustache(&["check", root.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(1));
+    let bad = root.join("sub/bad.mustache").display().to_string();
+    assert_eq!(text(&output.stdout), [
+        format!("{}:1:1: error: unclosed section `{{{{#a}}}}`\n", bad),
+        format!("{}:2:1: error: unexpected end of section `{{{{/b}}}}`\n", bad),
+        format!("{}:2:7: error: missing close delimiter `{{{{x`\n", bad),
+    ].concat());
+    assert_eq!(text(&output.stderr), "2 files, 3 errors, 0 warnings\n");
+
+    fs::remove_file(root.join("sub/bad.mustache")).unwrap();
+    let output = mustache(&["check", root.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!(text(&output.stdout), "");
+}
+
+#[test]
+fn check_reports_files_shadowed_by_earlier_directories() {
+    let root = directory("shadowed", &[
+        ("a/x.mustache", "{{>y}}"),
+        ("b/x.mustache", "{{#x}}"),
+        ("b/y.mustache", "{{y}}"),
+    ]);
+    let (a, b) = (root.join("a"), root.join("b"));
+    let output = mustache(&["lint", a.to_str().unwrap(), b.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(1));
+    let bad = b.join("x.mustache").display().to_string();
+    assert_eq!(text(&output.stdout), format!("{}:1:1: error: unclosed section `{{{{#x}}}}`\n", bad));
+    assert_eq!(text(&output.stderr), "3 files, 1 errors, 0 warnings\n");
+}
+
+#[test]
+fn lint_reports_warnings_as_json() {
+    let root = directory("lint", &[
+        ("base.hbs", "{{$body}}{{/body}}"),
+        ("page.hbs", "{{<base}}{{$body}}{{>row}}{{/body}}{{/base}}{{=<% %>=}}"),
+        ("list.hbs", "{{#?items}}{{>item}}{{/items}}"),
+        ("item.hbs", "{{.}}"),
+    ]);
+    let output = mustache(&["lint", root.to_str().unwrap(), "-e", "hbs", "--format", "json"], "");
+    assert_eq!(output.status.code(), Some(1));
+    let report = serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap();
+    let counts = (report["files"].as_u64(), report["errors"].as_u64(), report["warnings"].as_u64());
+    assert_eq!(counts, (Some(4), Some(2), Some(3)));
+    let diagnostics = report["diagnostics"].as_array().unwrap().iter()
+        .map(|diagnostic| format!(
+            "{} {}:{} {} {}",
+            diagnostic["path"].as_str().unwrap().strip_prefix(root.to_str().unwrap()).unwrap(),
+            diagnostic["line"], diagnostic["column"], diagnostic["severity"].as_str().unwrap(),
+            diagnostic["code"].as_str().unwrap()
+        ))
+        .collect::<Vec<_>>();
+    assert_eq!(diagnostics, [
+        "/list.hbs 1:1 error unclosed-section",
+        "/list.hbs 1:21 error unexpected-end-section",
+        "/list.hbs 1:21 warning wrong-close-qualifier",
+        "/page.hbs 1:19 warning missing-partial",
+        "/page.hbs 1:45 warning unused-delimiters",
+    ]);
+}
@@ -0,0 +1,74 @@
+extern crate mustache;
+use mustache::{Template, Context, ContextRef, ContextRefIterator, ContextValue};
+
+use std::collections::HashMap;
+
+
+// a context whose `label` and `empty` children are computed on request,
+// there being no field to borrow them from
+struct Counter {
+    count: usize,
+    name: String,
+}
+
+impl Context for Counter {
+    fn child(&self, name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        match name {
+            "name" => Some(&self.name),
+            _ => None
+        }
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        None
+    }
+
+    fn value(&self) -> ContextValue {
+        ContextValue::Text(self.count.to_string())
+    }
+
+    fn is_falsy(&self) -> bool {
+        false
+    }
+
+    fn computed(&self, name: &str) -> Option<Box<dyn Context + '_>> {
+        match name {
+            "label" => Some(Box::new(format!("{} x{}", self.name, self.count))),
+            "empty" => Some(Box::new(self.count == 0)),
+            _ => None
+        }
+    }
+}
+
+fn counters() -> HashMap<String, Vec<Counter>> {
+    HashMap::from([("counters".to_owned(), vec![
+        Counter { count: 2, name: "a".to_owned() },
+        Counter { count: 0, name: "b".to_owned() },
+    ])])
+}
+
+#[test]
+fn computed_children_are_rendered() {
+    let template = Template::from(
+        "{{#counters}}{{label}}{{#empty}} (empty){{/empty}}{{^empty}} (used){{/empty}};{{/counters}}"
+    ).unwrap();
+    assert_eq!(template.render(&counters()), "a x2 (used);b x0 (empty);");
+}
+
+#[test]
+fn computed_children_are_found_by_dotted_names() {
+    let counter = HashMap::from([("counter".to_owned(), Counter { count: 1, name: "c".to_owned() })]);
+    let template = Template::from("{{counter.label}} {{#counter.empty}}no{{/counter.empty}}").unwrap();
+    assert_eq!(template.render(&counter), "c x1 ");
+}
+
+#[test]
+fn children_in_the_stack_come_first() {
+    let data: HashMap<String, Box<dyn Context>> = HashMap::from([
+        ("label".to_owned(), Box::new("root".to_owned()) as Box<dyn Context>),
+        ("counters".to_owned(), Box::new(counters().remove("counters").unwrap())),
+    ]);
+    // `label` resolves in the root before being computed in a counter
+    let template = Template::from("{{#counters}}{{label}};{{/counters}}").unwrap();
+    assert_eq!(template.render(&data), "root;root;");
+}
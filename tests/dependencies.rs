@@ -0,0 +1,53 @@
+extern crate mustache;
+use mustache::{TemplateMap, ReferenceKind};
+
+use std::collections::HashMap;
+
+
+fn map(templates: &[(&str, &str)]) -> TemplateMap {
+    TemplateMap::new(templates.iter().copied().collect::<HashMap<_, _>>()).unwrap()
+}
+
+#[test]
+fn graph_and_missing_partials() {
+    let templates = map(&[
+        ("page", "{{<layout}}{{$body}}{{>item}}{{/body}}{{/layout}}"),
+        ("layout", "{{$body}}{{/body}}\n{{>footer}}"),
+        ("item", "{{name}}{{>*kind}}"),
+    ]);
+    let dependencies = templates.dependencies();
+    let graph = dependencies.graph.iter()
+        .map(|(name, used)| format!("{} -> {}", name, used.iter().cloned().collect::<Vec<_>>().join(",")))
+        .collect::<Vec<_>>();
+    assert_eq!(graph, ["item -> ", "layout -> ", "page -> item,layout"]);
+
+    assert_eq!(dependencies.missing.len(), 1);
+    let (template, reference) = &dependencies.missing[0];
+    assert_eq!((template.as_str(), reference.name.as_str()), ("layout", "footer"));
+    assert_eq!((reference.span.line, reference.span.column), (2, 1));
+
+    assert_eq!(dependencies.dynamic.len(), 1);
+    let (template, reference) = &dependencies.dynamic[0];
+    assert_eq!((template.as_str(), reference.name.as_str()), ("item", "kind"));
+    assert_eq!(reference.kind, ReferenceKind::DynamicPartial);
+
+    assert!(dependencies.cycles.is_empty());
+    assert!(!dependencies.is_valid());
+}
+
+#[test]
+fn cycles() {
+    let templates = map(&[
+        ("a", "{{>b}}"),
+        ("b", "{{>c}}{{>d}}"),
+        ("c", "{{>a}}"),
+        ("d", "{{#children}}{{>d}}{{/children}}"),
+        ("e", "{{>a}}"),
+    ]);
+    let dependencies = templates.dependencies();
+    assert_eq!(dependencies.cycles, [vec!["a", "b", "c"], vec!["d"]]);
+    assert!(dependencies.missing.is_empty());
+    assert!(!dependencies.is_valid());
+
+    assert!(map(&[("a", "{{>b}}"), ("b", "b")]).dependencies().is_valid());
+}
@@ -0,0 +1,100 @@
+extern crate mustache;
+use mustache::{Template, CompileOptions, TemplateMap, ErrorKind};
+
+use std::collections::HashMap;
+
+
+#[test]
+fn unexpected_end_section_is_located() {
+    let err = Template::from("abc\n  {{#x}}{{/y}}").err().unwrap();
+    assert_eq!(err.kind, ErrorKind::UnexpectedEndSection);
+    assert_eq!(err.offset, 12);
+    assert_eq!((err.line, err.column), (2, 9));
+    assert_eq!(err.tag, "{{/y}}");
+    assert_eq!(err.name, None);
+}
+
+#[test]
+fn missing_close_delimiter_is_located() {
+    let err = Template::from("é {{x\nmore").err().unwrap();
+    assert_eq!(err.kind, ErrorKind::MissingCloseDelimiter);
+    assert_eq!(err.offset, 3);
+    assert_eq!((err.line, err.column), (1, 3));
+    assert_eq!(err.tag, "{{x");
+}
+
+#[test]
+fn invalid_delimiters_are_located() {
+    let err = Template::from("\n\n{{=| | |=}}").err().unwrap();
+    assert_eq!(err.kind, ErrorKind::InvalidDelimiters);
+    assert_eq!((err.line, err.column), (3, 1));
+    assert_eq!(err.tag, "{{=| | |=}}");
+}
+
+#[test]
+fn errors_after_delimiters_change_use_new_delimiters() {
+    let err = Template::from("{{=<% %>=}}<%#a%><%/b%>").err().unwrap();
+    assert_eq!(err.kind, ErrorKind::UnexpectedEndSection);
+    assert_eq!(err.tag, "<%/b%>");
+}
+
+#[test]
+fn template_map_error_is_named() {
+    let input = vec![("good", "{{x}}"), ("bad", "{{/x}}")]
+        .into_iter().collect::<HashMap<_, _>>();
+    let err = TemplateMap::new(input).err().unwrap();
+    assert_eq!(err.name.as_deref(), Some("bad"));
+    assert_eq!(err.to_string(), "bad:1:1: unexpected end of section `{{/x}}`");
+}
+
+#[test]
+fn unclosed_tags_are_located_at_open_tag() {
+    for (text, tag) in [
+        ("{{#items}}hello", "{{#items}}"),
+        ("{{^items}}hello", "{{^items}}"),
+        ("{{$block}}hello", "{{$block}}"),
+        ("{{<parent}}hello", "{{<parent}}"),
+    ] {
+        let err = Template::from(text).err().unwrap();
+        assert_eq!(err.kind, ErrorKind::UnclosedSection);
+        assert_eq!((err.offset, err.tag.as_str()), (0, tag));
+    }
+    let err = Template::from("x\n{{#a}}{{#b}}{{/b}}").err().unwrap();
+    assert_eq!(err.kind, ErrorKind::UnclosedSection);
+    assert_eq!((err.line, err.column, err.tag.as_str()), (2, 1, "{{#a}}"));
+}
+
+#[test]
+fn lenient_mode_closes_sections_at_end_of_input() {
+    let options = CompileOptions { lenient: true, ..Default::default() };
+    let template = Template::compile("{{#items}}<{{.}}>", &options).unwrap();
+    let data = serde_json::json!({ "items": [1, 2] });
+    assert_eq!(template.render(&data), "<1><2>");
+}
+
+#[test]
+fn check_reports_all_errors() {
+    let text = "{{=| | |=}}\n{{#a}}{{#b}}{{/a}}\n{{/c}}{{^d}}{{#e}}\n{{x";
+    let errors = Template::check(text);
+    let found = errors.iter()
+        .map(|err| (err.kind.clone(), err.line, err.tag.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(found, vec![
+        (ErrorKind::InvalidDelimiters, 1, "{{=| | |=}}"),
+        (ErrorKind::UnexpectedEndSection, 2, "{{/a}}"),
+        (ErrorKind::UnexpectedEndSection, 3, "{{/c}}"),
+        (ErrorKind::UnclosedSection, 3, "{{^d}}"),
+        (ErrorKind::UnclosedSection, 3, "{{#e}}"),
+        (ErrorKind::MissingCloseDelimiter, 4, "{{x"),
+    ]);
+    assert!(errors.windows(2).all(|pair| pair[0].offset < pair[1].offset));
+}
+
+#[test]
+fn check_first_error_matches_compile_error() {
+    let text = "{{#a}}{{#b}}{{/a}}{{/b}}";
+    let errors = Template::check(text);
+    assert_eq!(errors.len(), 2);
+    assert_eq!(Template::from(text).err(), Some(errors[0].clone()));
+    assert!(Template::check("{{#a}}{{b}}{{/a}}").is_empty());
+}
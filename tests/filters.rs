@@ -0,0 +1,94 @@
+extern crate mustache;
+use mustache::{
+    Template, CompileOptions, RenderOptions, RenderErrorKind, ErrorKind, Filters, Lambdas, Node
+};
+
+use serde_json::json;
+
+
+fn compile(text: &str) -> Template {
+    Template::compile(text, &CompileOptions { filters: true, ..Default::default() }).unwrap()
+}
+
+#[test]
+fn filters_are_disabled_by_default() {
+    let template = Template::from("{{a|upper}} {{ a | upper }}").unwrap();
+    let data = json!({ "a": "x", "a|upper": "y", "a | upper": "z" });
+    assert_eq!(template.render(&data), "y z");
+    assert_eq!(compile("{{a|upper}}").render(&data), "X");
+}
+
+#[test]
+fn filters_apply_in_order_before_escaping() {
+    let data = json!({ "item": { "name": " fish & chips " }, "html": "<b>", "price": 2 });
+    let template = compile("[{{ item.name | trim | capitalize | truncate:6:... }}] {{{html|upper}}} {{&html|lower}}");
+    assert_eq!(template.render(&data), "[Fish &amp;...] <B> <b>");
+    let template = compile("{{#item}}{{name | replace:\" \":_}}{{/item}} {{price | fixed:2}}");
+    assert_eq!(template.render(&data), "_fish_&amp;_chips_ 2.00");
+}
+
+#[test]
+fn missing_values_are_filtered() {
+    let template = compile("{{missing | default:\"not set\"}} {{missing | upper}}.");
+    assert_eq!(template.render(&json!({})), "not set .");
+    let options = RenderOptions { strict: true, ..Default::default() };
+    let err = template.render_with_options(&json!({}), None, &options).unwrap_err();
+    assert_eq!(err.kind, RenderErrorKind::MissingVariable);
+}
+
+#[test]
+fn registry_filters_hide_builtins() {
+    let mut filters = Filters::new();
+    filters
+        .add("upper", |value, _| format!("<{}>", value))
+        .add("wrap", |value, args| format!("{}{}{}", args[0], value, args[1]));
+    assert!(filters.contains("wrap") && filters.contains("lower") && !filters.contains("other"));
+
+    let template = compile("{{a | upper | wrap:(:)}}");
+    let options = RenderOptions { filters: Some(&filters), ..Default::default() };
+    assert_eq!(template.render_with_options(&json!({ "a": "x" }), None, &options).unwrap(), "(&lt;x&gt;)");
+}
+
+#[test]
+fn unknown_filters() {
+    let template = compile("{{a | lower}}\n  {{a | nope | upper}}");
+    let data = json!({ "a": "Xy" });
+    assert_eq!(template.render(&data), "xy\n  XY");
+
+    let options = RenderOptions { strict: true, ..Default::default() };
+    let err = template.render_with_options(&data, None, &options).unwrap_err();
+    assert_eq!((err.kind, err.name.as_str(), err.line, err.column), (RenderErrorKind::UnknownFilter, "nope", 2, 3));
+}
+
+#[test]
+fn invalid_filters_are_compile_errors() {
+    let options = CompileOptions { filters: true, ..Default::default() };
+    for (text, column) in [("ab {{a |}}", 4), ("{{ | a}}", 1), ("{{a}} {{{a | \"x}}}", 7)] {
+        let err = Template::compile(text, &options).err().unwrap();
+        assert_eq!((err.kind, err.column), (ErrorKind::InvalidFilter, column), "{}", text);
+    }
+}
+
+#[test]
+fn filters_apply_to_lambdas() {
+    let mut lambdas = Lambdas::new();
+    lambdas
+        .lambda0("name", || "{{first}}".to_owned())
+        .helper("tag", |helper| helper.write_escaped("<x>"));
+    let data = json!({ "first": "ann" });
+    let template = compile("{{name | upper}} {{tag | upper}}");
+    assert_eq!(template.render(&lambdas.overlay(&data)), "ANN &LT;X&GT;");
+}
+
+#[test]
+fn nodes_have_filters() {
+    let template = compile("{{a | truncate:2:\"…\"}}{{b}}");
+    let filters = template.nodes().iter()
+        .map(|node| match node {
+            Node::Variable { filters, .. } =>
+                filters.iter().map(|filter| format!("{}{:?}", filter.name(), filter.args())).collect::<Vec<_>>(),
+            _ => vec![]
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(filters, [vec![r#"truncate["2", "…"]"#.to_owned()], vec![]]);
+}
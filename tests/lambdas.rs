@@ -0,0 +1,131 @@
+extern crate mustache;
+use mustache::{
+    Template, TemplateMap, Lambdas, YamlValue, MapsAndLists,
+    ContextValue, RenderOptions, RenderErrorKind
+};
+
+use std::collections::HashMap;
+use serde_json::json;
+
+
+#[test]
+fn lambdas_hide_root_names_only() {
+    let mut lambdas = Lambdas::new();
+    lambdas.lambda0("name", || "lambda".to_owned());
+    let data = json!({ "name": "root", "items": [{ "name": "item" }, {}] });
+    let template = Template::from("{{name}} {{#items}}{{name}} {{/items}}").unwrap();
+    assert_eq!(template.render(&lambdas.overlay(&data)), "lambda item lambda ");
+}
+
+#[test]
+fn sections_in_partials_pass_their_content() {
+    let mut lambdas = Lambdas::new();
+    lambdas.lambda1("upper", |text| text.to_uppercase());
+    lambdas.lambda1("twice", |text| format!("{}{}", text, text));
+    let partials = TemplateMap::new(
+        vec![("p", "[{{#upper}}x{{/upper}}]")].into_iter().collect::<HashMap<_, _>>()
+    ).unwrap();
+    let data = serde_yaml::from_str::<YamlValue>("x: 1").unwrap();
+    let template = Template::from("{{#twice}}{{>p}}{{#upper}}a{{/upper}}{{/twice}}").unwrap();
+    assert_eq!(template.render_with_partials(&lambdas.overlay(&data), &partials), "[X]A[X]A");
+}
+
+#[test]
+fn sections_in_blocks_pass_their_content() {
+    let mut lambdas = Lambdas::new();
+    lambdas.lambda1("wrap", |text| format!("[{}]", text));
+    lambdas.helper("raw", |helper| {
+        let section = helper.section().unwrap_or_default().to_owned();
+        helper.write(&section)
+    });
+    let partials = TemplateMap::new(vec![
+        ("base", "<{{$body}}{{/body}}>"),
+        ("page", "{{<base}}{{$body}}{{#wrap}}page {{x}}{{/wrap}}{{/body}}{{/base}}"),
+        ("nested", "{{<page}}{{$body}}{{<base}}{{$body}}{{#raw}}{{x}}!{{/raw}}{{/body}}{{/base}}{{/body}}{{/page}}"),
+    ].into_iter().collect::<HashMap<_, _>>()).unwrap();
+    let data = json!({ "x": "one" });
+    let data = lambdas.overlay(&data);
+
+    let template = Template::from("{{<base}}{{$body}}a long prefix {{#wrap}}{{x}} b{{/wrap}}{{/body}}{{/base}}").unwrap();
+    assert_eq!(template.render_with_partials(&data, &partials), "<a long prefix [one b]>");
+    let template = Template::from("{{>page}}|{{>nested}}").unwrap();
+    assert_eq!(template.render_with_partials(&data, &partials), "<[page one]>|<<{{x}}!>>");
+}
+
+#[test]
+fn arity_follows_position() {
+    let mut lambdas = Lambdas::new();
+    lambdas.lambda0("zero", || "0".to_owned());
+    lambdas.lambda1("one", |text| format!("<{}>", text));
+    let template = Template::from("{{#zero}}ignored{{/zero}} {{one}} {{^one}}no{{/one}}").unwrap();
+    assert_eq!(template.render(&lambdas.overlay(&json!({}))), "0 &lt;&gt; ");
+}
+
+#[test]
+fn helpers_render_the_section_for_each_item() {
+    let mut lambdas = Lambdas::new();
+    lambdas.helper("join", |helper| {
+        let items = helper.context("items").and_then(|items| items.children());
+        for (index, item) in items.into_iter().flatten().enumerate() {
+            if index > 0 {
+                helper.write(" & ")?;
+            }
+            helper.render_with(item)?;
+        }
+        Ok(())
+    });
+    let data = json!({ "items": ["a", "b", "c"], "sep": "-" });
+    let template = Template::from("{{#join}}{{sep}}{{.}}{{/join}}").unwrap();
+    assert_eq!(template.render(&lambdas.overlay(&data)), "-a & -b & -c");
+}
+
+#[test]
+fn helpers_write_text_as_is() {
+    let mut lambdas = Lambdas::new();
+    lambdas.helper("raw", |helper| {
+        let text = match helper.context("name").map(|name| name.value()) {
+            Some(ContextValue::Text(text)) => text,
+            _ => String::new()
+        };
+        helper.write("{{not a tag}} ")?;
+        helper.write_escaped(&text)?;
+        if let Some(section) = helper.section() {
+            helper.write(&format!(" [{}] ", section))?;
+            helper.render()?;
+        }
+        Ok(())
+    });
+    let data = json!({ "name": "<b>" });
+    let template = Template::from("{{raw}}|{{#raw}}{{name}}{{/raw}}").unwrap();
+    assert_eq!(
+        template.render(&lambdas.overlay(&data)),
+        "{{not a tag}} &lt;b&gt;|{{not a tag}} &lt;b&gt; [{{name}}] &lt;b&gt;"
+    );
+}
+
+#[test]
+fn helpers_report_render_errors() {
+    let mut lambdas = Lambdas::new();
+    lambdas.helper("twice", |helper| {
+        helper.render()?;
+        helper.render()
+    });
+    let data = json!({});
+    let template = Template::from("{{#twice}}\n{{missing}}{{/twice}}").unwrap();
+    let options = RenderOptions { strict: true, ..Default::default() };
+    let err = template.render_with_options(&lambdas.overlay(&data), None, &options).err().unwrap();
+    assert_eq!((err.kind, err.line), (RenderErrorKind::MissingVariable, 2));
+}
+
+#[test]
+fn helpers_in_maps_and_lists() {
+    let data = MapsAndLists::mapping(HashMap::from([
+        ("count".to_owned(), MapsAndLists::helper(|helper| {
+            let count = helper.context("list").and_then(|list| list.children()).map_or(0, |items| items.count());
+            helper.write(&count.to_string())
+        })),
+        ("list".to_owned(), MapsAndLists::sequence(vec![MapsAndLists::null(), MapsAndLists::bool(true)])),
+    ]));
+    let template = Template::from("{{count}} {{^count}}no{{/count}}").unwrap();
+    assert_eq!(template.render(&data), "2 ");
+}
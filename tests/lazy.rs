@@ -0,0 +1,141 @@
+extern crate mustache;
+use mustache::{Template, MapsAndLists, LazyContext, Lambdas, Context, ContextRef, ContextRefIterator, ContextValue};
+
+use std::{cell::Cell, collections::HashMap, rc::Rc};
+use serde_json::json;
+
+
+// rows produced by a query, counting calls
+fn rows(calls: &Rc<Cell<usize>>) -> MapsAndLists {
+    let calls = Rc::clone(calls);
+    MapsAndLists::lazy(move || {
+        calls.set(calls.get() + 1);
+        MapsAndLists::sequence(["a", "b"].iter().map(|name| MapsAndLists::mapping(HashMap::from([
+            ("name".to_owned(), MapsAndLists::text(name)),
+        ]))).collect())
+    })
+}
+
+#[test]
+fn lazy_values_are_produced_once_per_render() {
+    let calls = Rc::new(Cell::new(0));
+    let data = MapsAndLists::mapping(HashMap::from([
+        ("rows".to_owned(), rows(&calls)),
+        ("title".to_owned(), MapsAndLists::text("t")),
+    ]));
+
+    let template = Template::from("{{title}}").unwrap();
+    assert_eq!(template.render(&data), "t");
+    assert_eq!(calls.get(), 0);
+
+    let template = Template::from("{{#rows}}{{name}}{{/rows}} {{^rows}}none{{/rows}} {{rows.0.name}}").unwrap();
+    assert_eq!(template.render(&data), "ab  ");
+    assert_eq!(calls.get(), 1);
+    assert_eq!(template.render(&data), "ab  ");
+    assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn lazy_values_are_fresh_in_each_render() {
+    let calls = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&calls);
+    let data = MapsAndLists::mapping(HashMap::from([
+        ("items".to_owned(), MapsAndLists::sequence(vec![
+            MapsAndLists::text("a"),
+            MapsAndLists::text("b"),
+        ])),
+        ("count".to_owned(), MapsAndLists::lazy(move || {
+            counter.set(counter.get() + 1);
+            MapsAndLists::mapping(HashMap::from([
+                ("n".to_owned(), MapsAndLists::text(&counter.get().to_string())),
+                ("inner".to_owned(), MapsAndLists::lazy(|| MapsAndLists::text("inner"))),
+            ]))
+        })),
+    ]));
+    let template = Template::from("{{#items}}{{.}}{{count.n}} {{/items}}{{#count}}{{inner}}{{/count}}").unwrap();
+    assert_eq!(template.render(&data), "a1 b1 inner");
+    assert_eq!(template.render(&data), "a2 b2 inner");
+    assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn lazy_values_can_be_any_context() {
+    let user = MapsAndLists::lazy(|| MapsAndLists::mapping(HashMap::from([
+        ("name".to_owned(), MapsAndLists::text("john")),
+        ("admin".to_owned(), MapsAndLists::bool(false)),
+    ])));
+    let empty = MapsAndLists::lazy(|| MapsAndLists::sequence(vec![]));
+    let data = MapsAndLists::mapping(HashMap::from([
+        ("user".to_owned(), user),
+        ("empty".to_owned(), empty),
+    ]));
+    let template = Template::from("{{user.name}}{{^user.admin}} (user){{/user.admin}}{{^empty}} -{{/empty}}").unwrap();
+    assert_eq!(template.render(&data), "john (user) -");
+}
+
+#[test]
+fn lazy_context_wraps_other_contexts() {
+    let calls = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&calls);
+    let mut data = LazyContext::new(move || {
+        counter.set(counter.get() + 1);
+        json!({ "n": counter.get() })
+    });
+    assert!(!data.is_evaluated());
+
+    let template = Template::from("{{n}}{{n}}").unwrap();
+    assert_eq!(template.render(&data), "11");
+    assert_eq!(template.render(&data), "22");
+    assert!(!data.is_evaluated());
+
+    // the result is kept when used outside of the renderer, as in an overlay
+    let mut lambdas = Lambdas::new();
+    assert_eq!(data.get(), &json!({ "n": 3 }));
+    assert_eq!(template.render(&lambdas.overlay(&data)), "33");
+    assert!(data.is_evaluated());
+    data.reset();
+    assert_eq!(data.get(), &json!({ "n": 4 }));
+    assert_eq!(calls.get(), 4);
+
+    // lambdas in lazy contexts are called
+    lambdas.lambda0("n", || "lambda".to_owned());
+    let overlay = LazyContext::new(|| json!({}));
+    assert_eq!(template.render(&lambdas.overlay(&overlay)), "lambdalambda");
+}
+
+
+// the lazy context is at the address of its parent
+#[repr(C)]
+struct Inner {
+    rows: LazyContext<Vec<String>>,
+    name: String,
+}
+
+impl Context for Inner {
+    fn child(&self, name: &str, _section: Option<(usize, usize)>) -> Option<ContextRef<'_>> {
+        match name {
+            "rows" => Some(&self.rows),
+            _ => None
+        }
+    }
+
+    fn children(&self) -> Option<ContextRefIterator<'_>> {
+        None
+    }
+
+    fn value(&self) -> ContextValue {
+        ContextValue::Text(self.name.clone())
+    }
+
+    fn is_falsy(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn lazy_first_fields_are_distinct_from_their_parent() {
+    let inner = Inner { rows: LazyContext::new(|| vec!["a".to_owned()]), name: "INNER".to_owned() };
+    let data = HashMap::from([("inner".to_owned(), inner)]);
+    let template = Template::from("{{#inner.rows}}[{{.}}]{{/inner.rows}} [{{inner}}]").unwrap();
+    assert_eq!(template.render(&data), "[a] [INNER]");
+}
@@ -0,0 +1,51 @@
+extern crate mustache;
+use mustache::{Template, LintKind, Span};
+
+
+fn lints(input: &str) -> Vec<(LintKind, String, usize, usize)> {
+    Template::lint(input).into_iter()
+        .map(|lint| (lint.kind, lint.tag, lint.span.line, lint.span.column))
+        .collect()
+}
+
+#[test]
+fn clean_templates_have_no_lints() {
+    assert!(lints("{{#a}}{{b}}{{/a}}{{=<% %>=}}<%c%><%={{ }}=%>{{>d}}").is_empty());
+    assert!(lints("{{$title}}default{{/title}}").is_empty());
+    assert!(lints("{{<base}}{{$a}}{{$b}}x{{/b}}{{/a}}{{$c}}{{/c}}{{/base}}").is_empty());
+}
+
+#[test]
+fn unused_delimiters() {
+    assert_eq!(lints("{{=<% %>=}}\n<%={{ }}=%>{{x}}{{={{ }}=}}\n{{=[ ]=}}"), [
+        (LintKind::UnusedDelimiters, "{{=<% %>=}}".to_owned(), 1, 1),
+        (LintKind::UnusedDelimiters, "{{={{ }}=}}".to_owned(), 2, 17),
+        (LintKind::UnusedDelimiters, "{{=[ ]=}}".to_owned(), 3, 1),
+    ]);
+}
+
+#[test]
+fn wrong_close_qualifier() {
+    let input = "{{#?items}}\n  {{#x}}{{/x}}\n{{/items}}";
+    assert_eq!(lints(input), [(LintKind::WrongCloseQualifier, "{{/items}}".to_owned(), 3, 1)]);
+    assert!(!Template::check(input).is_empty());
+    assert_eq!(lints("{{<*p}}{{/p}}{{#a}}{{/?a}}").len(), 2);
+}
+
+#[test]
+fn blocks_outside_parents() {
+    assert_eq!(lints("{{<base}}{{#a}}{{$x}}{{/x}}{{/a}}{{/base}}\n{{$y}}{{/y}}"), [
+        (LintKind::BlockOutsideParent, "{{$x}}".to_owned(), 1, 16),
+        (LintKind::BlockOutsideParent, "{{$y}}".to_owned(), 2, 1),
+    ]);
+}
+
+#[test]
+fn duplicate_blocks() {
+    let input = "{{<base}}{{$a}}{{/a}}{{<inner}}{{$a}}{{/a}}{{/inner}}{{$a}}{{/a}}{{/base}}";
+    let lint = &Template::lint(input)[0];
+    assert_eq!(lint.kind, LintKind::DuplicateBlock);
+    assert_eq!(lint.span, Span { start: 53, end: 59, line: 1, column: 54 });
+    assert_eq!(lint.to_string(), "1:54: duplicate block `{{$a}}`");
+    assert_eq!(Template::lint(input).len(), 1);
+}
@@ -0,0 +1,206 @@
+extern crate mustache;
+use mustache::{
+    Template, TemplateMap, JsonValue, MapsAndLists,
+    RenderOptions, RenderError, RenderErrorKind
+};
+
+use std::{io, collections::HashMap};
+use serde_json::json;
+
+
+#[test]
+fn render_to_fmt_write() {
+    let template = Template::from("{{#items}}<{{.}}>{{/items}}").unwrap();
+    let data = json!({ "items": ["a", "b&c"] });
+    let mut out = String::from("items: ");
+    template.render_to(&data, &mut out).unwrap();
+    assert_eq!(out, "items: <a><b&amp;c>");
+}
+
+#[test]
+fn write_to_io_write_with_partials() {
+    let template = Template::from("[{{>item}}]").unwrap();
+    let partials = TemplateMap::new(
+        vec![("item", "  {{name}}\n")].into_iter().collect::<HashMap<_, _>>()
+    ).unwrap();
+    let data = json!({ "name": "x" });
+    let mut out = Vec::new();
+    template.write_to_with_partials(&data, &partials, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "[  x\n]");
+}
+
+#[test]
+fn write_to_propagates_io_errors() {
+    struct Full(usize);
+    impl io::Write for Full {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 < buf.len() {
+                return Err(io::Error::new(io::ErrorKind::WriteZero, "full"));
+            }
+            self.0 -= buf.len();
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    let template = Template::from("{{#items}}{{.}}{{/items}}").unwrap();
+    let data = serde_json::from_str::<JsonValue>(r#"{"items": ["12345", "67890"]}"#).unwrap();
+    let err = template.write_to(&data, &mut Full(8)).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+    assert!(template.write_to(&data, &mut Full(10)).is_ok());
+}
+
+#[test]
+fn strict_mode_renders_complete_data() {
+    let template = Template::from("{{#a}}{{b.c}}{{/a}}{{^d}}-{{/d}}").unwrap();
+    let data = json!({ "a": true, "b": { "c": 1 }, "d": false });
+    let options = RenderOptions { strict: true, ..Default::default() };
+    assert_eq!(template.render_with_options(&data, None, &options).unwrap(), "1-");
+}
+
+#[test]
+fn strict_mode_reports_missing_names() {
+    let data = json!({ "a": { "b": {} }, "list": [1] });
+    let options = RenderOptions { strict: true, ..Default::default() };
+    for (text, kind, name, key, column) in [
+        ("x {{a.b.c}}", RenderErrorKind::MissingVariable, "a.b.c", "c", 3),
+        ("{{x.b}}", RenderErrorKind::MissingVariable, "x.b", "x", 1),
+        ("{{#list}}\n {{#y}}{{/y}}{{/list}}", RenderErrorKind::MissingSection, "y", "y", 2),
+        ("{{^z}}{{/z}}", RenderErrorKind::MissingSection, "z", "z", 1),
+        ("{{>p}}", RenderErrorKind::MissingPartial, "p", "p", 1),
+        ("{{>*q}}", RenderErrorKind::MissingVariable, "q", "q", 1),
+    ] {
+        let template = Template::from(text).unwrap();
+        let err = template.render_with_options(&data, None, &options).err().unwrap();
+        assert_eq!(
+            (err.kind, err.name.as_str(), err.key.as_str(), err.column),
+            (kind, name, key, column),
+            "{}", text
+        );
+        assert!(template.render_with_options(&data, None, &RenderOptions::default()).is_ok());
+    }
+}
+
+#[test]
+fn strict_mode_locates_errors_in_partials() {
+    let template = Template::from("{{>item}}").unwrap();
+    let partials = TemplateMap::new(
+        vec![("item", "ok\n{{missing}}")].into_iter().collect::<HashMap<_, _>>()
+    ).unwrap();
+    let options = RenderOptions { strict: true, ..Default::default() };
+    let err = template.render_with_options(&json!({}), Some(&partials), &options).err().unwrap();
+    assert_eq!(err.template.as_deref(), Some("item"));
+    assert_eq!((err.line, err.column), (2, 1));
+    assert_eq!(err.to_string(), "item:2:1: missing variable `missing`");
+
+    let mut out = Vec::new();
+    let err = template.write_to_with_options(&json!({}), Some(&partials), &options, &mut out).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+    assert!(err.get_ref().unwrap().is::<RenderError>());
+}
+
+#[test]
+fn nested_parents_resolve_outermost_blocks() {
+    let partials = TemplateMap::new(vec![
+        ("base", "<h1>{{$title}}base{{/title}}</h1>{{$body}}{{/body}}{{>footer}}"),
+        ("layout", "{{<base}}{{$title}}layout{{/title}}{{$body}}[{{$content}}none{{/content}}]{{/body}}{{/base}}"),
+        ("footer", "{{$title}}footer{{/title}}"),
+    ].into_iter().collect::<HashMap<_, _>>()).unwrap();
+    let data = json!({ "items": [1, 2] });
+
+    let template = Template::from("{{<layout}}{{$title}}page{{/title}}{{/layout}}").unwrap();
+    assert_eq!(template.render_with_partials(&data, &partials), "<h1>page</h1>[none]footer");
+
+    let template = Template::from(
+        "{{<layout}}{{$content}}{{#items}}{{$item}}{{.}}{{/item}}{{/items}}{{/content}}{{/layout}}"
+    ).unwrap();
+    assert_eq!(template.render_with_partials(&data, &partials), "<h1>layout</h1>[12]footer");
+}
+
+#[test]
+fn depth_limit_stops_recursive_partials() {
+    let partials = TemplateMap::new(vec![
+        ("tree", "<{{name}}{{#children}}{{>tree}}{{/children}}>"),
+        ("loop", "{{>loop}}"),
+    ].into_iter().collect::<HashMap<_, _>>()).unwrap();
+    let data = json!({ "name": "a", "children": [{ "name": "b", "children": [{ "name": "c", "children": [] }] }] });
+    let options = RenderOptions { max_depth: Some(3), ..Default::default() };
+
+    let template = Template::from("{{>tree}}").unwrap();
+    assert_eq!(template.render_with_options(&data, Some(&partials), &options).unwrap(), "<a<b<c>>>");
+    let options = RenderOptions { max_depth: Some(2), ..Default::default() };
+    let err = template.render_with_options(&data, Some(&partials), &options).err().unwrap();
+    assert_eq!(err.kind, RenderErrorKind::DepthLimit);
+    assert_eq!(err.template.as_deref(), Some("tree"));
+    assert_eq!(err.to_string(), "tree:1:23: nesting depth limit exceeded `tree`");
+
+    let template = Template::from("x{{>loop}}").unwrap();
+    let options = RenderOptions { max_depth: Some(128), ..Default::default() };
+    let err = template.render_with_options(&data, Some(&partials), &options).err().unwrap();
+    assert_eq!((err.kind, err.template.as_deref()), (RenderErrorKind::DepthLimit, Some("loop")));
+}
+
+#[test]
+fn depth_is_unlimited_by_default() {
+    let partials = TemplateMap::new(vec![
+        ("tree", "<{{n}}{{#child}}{{>tree}}{{/child}}>"),
+    ].into_iter().collect::<HashMap<_, _>>()).unwrap();
+    let mut data = json!({ "n": 0, "child": false });
+    for n in 1..200 {
+        data = json!({ "n": n, "child": data });
+    }
+    let result = Template::from("{{>tree}}").unwrap().render_with_partials(&data, &partials);
+    assert!(result.starts_with("<199<198<197"));
+    assert!(result.ends_with(&format!("<1<0{}", ">".repeat(200))));
+}
+
+#[test]
+fn depth_limit_stops_recursive_lambdas() {
+    let data = MapsAndLists::mapping(HashMap::from([
+        ("again".to_owned(), MapsAndLists::lambda0(|| "+{{again}}".to_owned())),
+    ]));
+    let template = Template::from("{{again}}").unwrap();
+    let options = RenderOptions { max_depth: Some(4), ..Default::default() };
+    let err = template.render_with_options(&data, None, &options).err().unwrap();
+    assert_eq!((err.kind, err.name.as_str(), err.column), (RenderErrorKind::DepthLimit, "again", 2));
+}
+
+#[test]
+fn output_limit_stops_rendering() {
+    let template = Template::from("{{#items}}[{{.}}]{{/items}}").unwrap();
+    let data = json!({ "items": ["abc", "def"] });
+    let options = RenderOptions { max_output: Some(8), ..Default::default() };
+    let mut out = String::new();
+    let err = template.render_to_with_options(&data, None, &options, &mut out).err().unwrap();
+    assert_eq!(err.kind, RenderErrorKind::OutputLimit);
+    assert_eq!(err.to_string(), "output size limit exceeded");
+    assert_eq!(out, "[abc][");
+
+    let options = RenderOptions { max_output: Some(10), ..Default::default() };
+    assert_eq!(template.render_with_options(&data, None, &options).unwrap(), "[abc][def]");
+}
+
+#[test]
+fn iteration_limit_stops_sections() {
+    let template = Template::from("{{#rows}}\n-{{#cols}}.{{/cols}}{{/rows}}").unwrap();
+    let data = json!({ "rows": [{ "cols": [1, 2] }, { "cols": [3, 4] }] });
+    let options = RenderOptions { max_iterations: Some(6), ..Default::default() };
+    assert_eq!(template.render_with_options(&data, None, &options).unwrap(), "-..-..");
+
+    let options = RenderOptions { max_iterations: Some(5), ..Default::default() };
+    let err = template.render_with_options(&data, None, &options).err().unwrap();
+    assert_eq!(
+        (err.kind, err.name.as_str(), err.line, err.column),
+        (RenderErrorKind::IterationLimit, "cols", 2, 2)
+    );
+}
+
+#[test]
+fn loop_metadata_is_opt_in() {
+    let template = Template::from("{{#list}}{{@index}}{{.}}{{^@last}},{{/@last}}{{/list}}").unwrap();
+    let data = json!({ "list": ["a", "b"], "@index": "#" });
+    assert_eq!(template.render(&data), "#a,#b,");
+    let options = RenderOptions { loop_metadata: true, ..Default::default() };
+    assert_eq!(template.render_with_options(&data, None, &options).unwrap(), "0a,1b");
+}
@@ -0,0 +1,61 @@
+extern crate mustache;
+use mustache::Template;
+
+use serde_json::json;
+
+
+#[test]
+fn schema_of_nested_data() {
+    let template = Template::from(concat!(
+        "{{title}} {{user.name}} {{user.address.city}}\n",
+        "{{#items}}{{price}}{{#tags}}{{.}}{{/tags}}{{^sold}}{{stock}}{{/sold}}{{/items}}\n",
+        "{{#admin}}admin{{/admin}}{{#?lines}}-{{/?lines}}{{>*kind}}{{>footer}}"
+    )).unwrap();
+    let scalar = json!({ "type": ["string", "number", "boolean"] });
+    assert_eq!(template.schema(), json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "properties": {
+            "title": scalar,
+            "user": {
+                "type": "object",
+                "properties": {
+                    "name": scalar,
+                    "address": { "type": "object", "properties": { "city": scalar } }
+                }
+            },
+            "items": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "price": scalar,
+                        "tags": { "type": "array", "items": scalar },
+                        "sold": {},
+                        "stock": scalar
+                    }
+                }
+            },
+            "admin": {},
+            "lines": { "type": "array", "items": {} },
+            "kind": { "type": "string" }
+        }
+    }));
+}
+
+#[test]
+fn schema_merges_uses_of_a_name() {
+    let template = Template::from(
+        "{{#user}}{{name}}{{/user}}{{user.id}}{{#list}}{{a}}{{/list}}{{#list}}{{b}}{{/list}}{{#?list}}{{/?list}}"
+    ).unwrap();
+    let scalar = json!({ "type": ["string", "number", "boolean"] });
+    let schema = template.schema();
+    assert_eq!(schema["properties"]["user"], json!({
+        "type": "object",
+        "properties": { "id": scalar, "name": scalar }
+    }));
+    assert_eq!(schema["properties"]["list"], json!({
+        "type": "array",
+        "items": { "type": "object", "properties": { "a": scalar, "b": scalar } }
+    }));
+}
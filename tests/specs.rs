@@ -1,3 +1,6 @@
+// spec results are folded (not try_folded) so that every failing test is reported
+#![allow(clippy::manual_try_fold)]
+
 extern crate mustache;
 use mustache::{Template, TemplateMap, YamlValue};
 
@@ -7,7 +10,7 @@ use serde_yaml::Mapping as YamlMapping;
 
 #[test]
 fn spec_tests() -> Result<(), String> {
-    vec![
+    [
         "comments",
         "interpolation",
         "sections",
@@ -97,15 +100,15 @@ struct YamlTestSpec {
 fn yaml_spec(name: &str) -> Result<YamlSpecFile, String> {
     let path = format!("tests/specs/{}.yml", name);
     let text = fs::read_to_string(path).map_err(
-        |err| format!("io: {}", err.to_string())
+        |err| format!("io: {}", err)
     )?;
     serde_yaml::from_str::<YamlSpecFile>(&text).map_err(
-        |err| format!("yaml: {}", err.to_string())
+        |err| format!("yaml: {}", err)
     )
 }
 
 fn run_spec_test(test: &YamlTestSpec, log: bool) -> Result<(), String> {
-    let template = Template::from(&test.template)?;
+    let template = Template::from(&test.template).map_err(|err| err.to_string())?;
     let partials = if let Some(values) = &test.partials {
         values.iter().map(
             |(name, text)| {
@@ -117,7 +120,7 @@ fn run_spec_test(test: &YamlTestSpec, log: bool) -> Result<(), String> {
     } else {
         HashMap::new()
     };
-    let partials = TemplateMap::new(partials)?;
+    let partials = TemplateMap::new(partials).map_err(|err| err.to_string())?;
     let result = template.render_with_partials(
         &test.data, &partials
     );
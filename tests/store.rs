@@ -0,0 +1,162 @@
+extern crate mustache;
+use mustache::{Template, TemplateStore, FileSystemStore, HotReloadStore, StoreError, ErrorKind};
+
+use std::{fs, path::Path, time::{Duration, SystemTime}};
+use serde_json::json;
+
+mod common;
+use common::directory;
+
+
+#[test]
+fn resolves_nested_names() {
+    let root = directory("nested", &[
+        ("page.mustache", "{{<layouts/base}}{{$body}}hello{{/body}}{{/layouts/base}}"),
+        ("layouts/base.mustache", "<p>{{$body}}{{/body}}</p>"),
+        ("notes.txt", "ignored"),
+    ]);
+    let store = FileSystemStore::new(&[&root], ".mustache").unwrap();
+    let mut names = store.names().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["layouts/base", "page"]);
+    assert_eq!(store.path("layouts/base"), Some(root.join("layouts/base.mustache").as_path()));
+
+    let template = Template::from("{{>page}}").unwrap();
+    assert_eq!(template.render_with_partials(&json!({}), &store), "<p>hello</p>");
+}
+
+#[test]
+fn first_root_wins() {
+    let first = directory("first", &[("a.hbs", "first {{>b}}")]);
+    let second = directory("second", &[("a.hbs", "second"), ("b.hbs", "b"), ("c.mustache", "c")]);
+    let store = FileSystemStore::new(&[&first, &second], "hbs").unwrap();
+    let template = Template::from("{{>a}}{{>c}}").unwrap();
+    assert_eq!(template.render_with_partials(&json!({}), &store), "first b");
+}
+
+#[test]
+fn compiles_on_first_use() {
+    let root = directory("lazy", &[("a.mustache", "{{x}}"), ("b.mustache", "{{#x}}")]);
+    let store = FileSystemStore::new(&[&root], "mustache").unwrap();
+
+    // the broken template is never loaded
+    fs::write(root.join("a.mustache"), "[{{x}}]").unwrap();
+    assert_eq!(Template::from("{{>a}}").unwrap().render_with_partials(&json!({"x": 1}), &store), "[1]");
+
+    // and the cached template is kept
+    fs::write(root.join("a.mustache"), "{{x}}").unwrap();
+    assert!(store.get("a").is_some());
+    assert_eq!(Template::from("{{>a}}").unwrap().render_with_partials(&json!({"x": 1}), &store), "[1]");
+}
+
+#[test]
+fn reports_failing_file() {
+    let root = directory("failing", &[("ok.mustache", "ok"), ("bad/one.mustache", "x\n {{#s}}")]);
+    let store = FileSystemStore::new(&[&root], "mustache").unwrap();
+    assert!(store.get("bad/one").is_none());
+    assert!(store.load("missing").unwrap().is_none());
+
+    let path = root.join("bad/one.mustache");
+    match store.load("bad/one") {
+        Err(StoreError::Template(failing, err)) => {
+            assert_eq!(failing, &path);
+            assert_eq!((&err.kind, err.line, err.column), (&ErrorKind::UnclosedSection, 2, 2));
+        },
+        _ => panic!("expected a template error"),
+    }
+
+    fs::remove_file(root.join("ok.mustache")).unwrap();
+    let errors = store.load_all();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].to_string(), format!("{}:2:2: unclosed section `{{{{#s}}}}`", path.display()));
+    assert!(matches!(errors[1], StoreError::Io(..)));
+}
+
+#[test]
+fn matches_dotted_extensions() {
+    let root = directory("dotted", &[
+        ("page.html.mustache", "page"),
+        ("mail.txt.mustache", "mail"),
+        ("dir/.html.mustache", "hidden"),
+    ]);
+    let store = FileSystemStore::new(&[&root], ".html.mustache").unwrap();
+    assert_eq!(store.names().collect::<Vec<_>>(), ["page"]);
+    assert_eq!(Template::from("{{>page}}").unwrap().render_with_partials(&json!({}), &store), "page");
+}
+
+#[cfg(unix)]
+#[test]
+fn skips_linked_directories() {
+    let root = directory("links", &[("sub/a.mustache", "a")]);
+    std::os::unix::fs::symlink(&root, root.join("sub/loop")).unwrap();
+    std::os::unix::fs::symlink(root.join("sub/a.mustache"), root.join("b.mustache")).unwrap();
+    let store = FileSystemStore::new(&[&root], "mustache").unwrap();
+    let mut names = store.names().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["b", "sub/a"]);
+}
+
+#[test]
+fn missing_root_fails() {
+    let root = std::env::temp_dir().join("mustache-missing-root");
+    assert!(FileSystemStore::new(&[root], "mustache").is_err());
+}
+
+
+// write a file with a modification time distinct from previous writes
+fn update(path: &Path, text: &str, age: u64) {
+    fs::write(path, text).unwrap();
+    let modified = SystemTime::now() - Duration::from_secs(age);
+    fs::File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+}
+
+#[test]
+fn hot_reload_picks_up_changes() {
+    let root = directory("reload", &[]);
+    let store = HotReloadStore::new(&[&root], "mustache");
+    let template = Template::from("{{>a}}").unwrap();
+    let data = json!({ "x": 1 });
+    assert_eq!(template.render_with_partials(&data, &store), "");
+
+    update(&root.join("a.mustache"), "a{{x}}", 30);
+    assert_eq!(template.render_with_partials(&data, &store), "a1");
+
+    let previous = store.get("a").unwrap();
+    update(&root.join("a.mustache"), "A{{x}}", 20);
+    assert_eq!(template.render_with_partials(&data, &store), "A1");
+    assert_eq!(previous.render(&data), "a1");
+
+    fs::remove_file(root.join("a.mustache")).unwrap();
+    assert!(store.get("a").is_none());
+}
+
+#[test]
+fn hot_reload_keeps_last_good_version() {
+    let root = directory("last-good", &[]);
+    let path = root.join("page.hbs");
+    update(&path, "{{#x}}ok{{/x}}", 30);
+    let store = HotReloadStore::new(&[&root], ".hbs");
+    let template = Template::from("{{>page}}").unwrap();
+    let data = json!({ "x": true });
+    assert_eq!(template.render_with_partials(&data, &store), "ok");
+    assert!(store.error("page").is_none());
+
+    update(&path, "{{#x}}broken", 20);
+    assert_eq!(template.render_with_partials(&data, &store), "ok");
+    let err = store.error("page").unwrap();
+    assert_eq!(err.path(), path);
+    assert!(matches!(*err, StoreError::Template(_, ref err) if err.kind == ErrorKind::UnclosedSection));
+
+    update(&path, "{{#x}}fixed{{/x}}", 10);
+    assert_eq!(template.render_with_partials(&data, &store), "fixed");
+    assert!(store.error("page").is_none());
+}
+
+#[test]
+fn hot_reload_rejects_names_outside_roots() {
+    let root = directory("outside", &[("inner/a.mustache", "a")]);
+    let store = HotReloadStore::new(&[root.join("inner")], "mustache");
+    assert!(store.get("a").is_some());
+    assert!(store.get("../inner/a").is_none());
+    assert!(store.get("/a").is_none());
+}
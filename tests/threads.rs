@@ -0,0 +1,68 @@
+extern crate mustache;
+use mustache::{
+    Template, TemplateStore, TemplateMap, FileSystemStore, HotReloadStore, SyncMapsAndLists
+};
+
+use std::{collections::HashMap, sync::{Arc, atomic::{AtomicUsize, Ordering}}, thread};
+use serde_json::json;
+
+
+fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+
+#[test]
+fn templates_and_stores_are_send_and_sync() {
+    assert_send_sync::<Template>();
+    assert_send_sync::<TemplateMap>();
+    assert_send_sync::<FileSystemStore>();
+    assert_send_sync::<HotReloadStore>();
+    assert_send_sync::<dyn TemplateStore>();
+    assert_send_sync::<SyncMapsAndLists>();
+}
+
+#[test]
+fn render_shared_template_and_partials() {
+    let template = Arc::new(Template::from("{{#items}}{{>item}}{{/items}}").unwrap());
+    let partials: Arc<dyn TemplateStore> = Arc::new(TemplateMap::new(
+        vec![("item", "<{{.}}>")].into_iter().collect::<HashMap<_, _>>()
+    ).unwrap());
+
+    let handles = (0..4).map(|n| {
+        let template = Arc::clone(&template);
+        let partials = Arc::clone(&partials);
+        thread::spawn(move || {
+            let data = json!({ "items": [n, n + 1] });
+            template.render_with_partials(&data, partials.as_ref())
+        })
+    }).collect::<Vec<_>>();
+    let results = handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>();
+    assert_eq!(results, ["<0><1>", "<1><2>", "<2><3>", "<3><4>"]);
+}
+
+#[test]
+fn sync_lambdas_are_kept_per_thread() {
+    let source = Arc::from("{{#wrap}}{{name}}-{{/wrap}}{{count}}");
+    let template = Template::from(&source).unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&calls);
+    let context = SyncMapsAndLists::mapping(
+        vec![
+            (String::from("name"), SyncMapsAndLists::text("x")),
+            (String::from("wrap"), SyncMapsAndLists::lambda1(|s| format!("<{}>", s), &source)),
+            (String::from("count"), SyncMapsAndLists::lambda0(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                "!".to_owned()
+            })),
+        ].into_iter().collect::<HashMap<_, _>>()
+    );
+
+    thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                for _ in 0..10 {
+                    assert_eq!(template.render(&context), "<x->!");
+                }
+            });
+        }
+    });
+    assert_eq!(calls.load(Ordering::SeqCst), 80);
+}